@@ -1,9 +1,13 @@
+use crate::ingredients::normalize_name;
 use Effect::*;
-use enumset::EnumSetType;
+use enumset::{EnumSet, EnumSetType};
 use nalgebra::Vector2;
 
 /// An effect given by a drug
+///
+/// Sets of effects are stored as a single `u64` mask, where the bit of every effect is its index in `Effect::ALL`.
 #[derive(EnumSetType, Debug, Hash)]
+#[enumset(repr = "u64")]
 pub enum Effect {
     AntiGravity,
     Athletic,
@@ -79,6 +83,15 @@ impl Effect {
         Zombifying,
     ];
 
+    /// Finds the `Effect` with the given name, ignoring case, spaces and punctuation.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = normalize_name(name);
+        Self::ALL
+            .iter()
+            .find(|e| normalize_name(&e.to_string()) == name)
+            .copied()
+    }
+
     /// Returns the price modifier of this `Effect`.
     pub fn price_modifier(&self) -> f32 {
         match self {
@@ -335,3 +348,60 @@ pub fn get_total_addictiveness<I: IntoIterator<Item = Effect>>(effects: I) -> f3
         .sum::<f32>()
         .clamp(f32::MIN_POSITIVE, 1.0)
 }
+
+/// What a search requires of a single `Effect`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EffectConstraint {
+    Required,
+    Forbidden,
+    DontCare,
+}
+
+/// A set of constraints on the effects of a recipe, used to build search predicates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EffectConstraints {
+    pub required: EnumSet<Effect>,
+    pub forbidden: EnumSet<Effect>,
+    /// Whether every effect that isn't required is forbidden.
+    pub exact: bool,
+}
+
+impl EffectConstraints {
+    /// Creates `EffectConstraints` that only require the given effects.
+    pub fn requiring(required: EnumSet<Effect>) -> Self {
+        Self {
+            required,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the constraint on the given `Effect`.
+    pub fn get(&self, effect: Effect) -> EffectConstraint {
+        if self.required.contains(effect) {
+            EffectConstraint::Required
+        } else if self.forbidden.contains(effect) || self.exact {
+            EffectConstraint::Forbidden
+        } else {
+            EffectConstraint::DontCare
+        }
+    }
+
+    /// Replaces the constraint on the given `Effect`.
+    pub fn set(&mut self, effect: Effect, constraint: EffectConstraint) {
+        self.required.remove(effect);
+        self.forbidden.remove(effect);
+        match constraint {
+            EffectConstraint::Required => self.required.insert(effect),
+            EffectConstraint::Forbidden => self.forbidden.insert(effect),
+            EffectConstraint::DontCare => false,
+        };
+    }
+
+    /// Returns whether the given effect set satisfies these constraints.
+    pub fn is_satisfied_by(&self, effects: EnumSet<Effect>) -> bool {
+        match self.exact {
+            true => effects == self.required,
+            false => self.required.is_subset(effects) && self.forbidden.is_disjoint(effects),
+        }
+    }
+}
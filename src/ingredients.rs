@@ -1,6 +1,6 @@
-use crate::{effect::Effect, expenses::Expenses};
+use crate::{effect::Effect, expenses::Expenses, recipe::MAX_EFFECTS};
 use ahash::AHashMap;
-use enumset::EnumSet;
+use enumset::{EnumSet, EnumSetType};
 use std::sync::LazyLock;
 
 /// A base ingredient, probably for a `Recipe`.
@@ -36,6 +36,15 @@ impl Base {
         Base::Meth,
     ];
 
+    /// Finds the `Base` with the given name, ignoring case, spaces and punctuation.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = normalize_name(name);
+        Self::ALL
+            .iter()
+            .find(|b| normalize_name(&b.to_string()) == name)
+            .copied()
+    }
+
     /// Returns the addictiveness of this `Base`.
     pub fn addictiveness(&self) -> f32 {
         use Base::*;
@@ -97,7 +106,7 @@ impl Base {
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[derive(EnumSetType, Debug, Hash)]
 pub enum Intermediate {
     Addy,
     Banana,
@@ -117,6 +126,40 @@ pub enum Intermediate {
     Viagra,
 }
 
+/// `TRANSITIONS[i][e]` is the effect that the effect at index `e` of `Effect::ALL` turns into when the ingredient at index `i` of `Intermediate::ALL` is mixed in, if any.
+///
+/// Built once from `Intermediate::interactions` so that mixing never has to hash anything.
+static TRANSITIONS: LazyLock<Vec<[Option<Effect>; Effect::ALL.len()]>> = LazyLock::new(|| {
+    Intermediate::ALL
+        .iter()
+        .map(|i| {
+            let mut row = [None; Effect::ALL.len()];
+            for (from_effect, to_effect) in i.interactions() {
+                row[*from_effect as usize] = Some(*to_effect);
+            }
+            row
+        })
+        .collect()
+});
+
+/// `SOURCES[i][e]` is the set of effects that turn into the effect at index `e` of `Effect::ALL` when the ingredient at index `i` of `Intermediate::ALL` is mixed in.
+///
+/// The inverse of `TRANSITIONS`, used to walk back from a product to the ones it can be mixed from.
+static SOURCES: LazyLock<Vec<[EnumSet<Effect>; Effect::ALL.len()]>> = LazyLock::new(|| {
+    TRANSITIONS
+        .iter()
+        .map(|transitions| {
+            let mut row = [EnumSet::new(); Effect::ALL.len()];
+            for from_effect in Effect::ALL {
+                if let Some(to_effect) = transitions[*from_effect as usize] {
+                    row[to_effect as usize].insert(*from_effect);
+                }
+            }
+            row
+        })
+        .collect()
+});
+
 impl Intermediate {
     pub const ALL: &'static [Self] = &[
         Intermediate::Addy,
@@ -137,6 +180,15 @@ impl Intermediate {
         Intermediate::Viagra,
     ];
 
+    /// Finds the `Intermediate` with the given name, ignoring case, spaces and punctuation.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = normalize_name(name);
+        Self::ALL
+            .iter()
+            .find(|i| normalize_name(&i.name()) == name)
+            .copied()
+    }
+
     pub fn interactions(&self) -> &'static AHashMap<Effect, Effect> {
         macro_rules! generate_interactions_static {
             ($intermediate:expr) => {{
@@ -363,24 +415,76 @@ impl Intermediate {
         }
     }
 
+    /// Returns the effect that `effect` turns into when this ingredient is mixed in, if any.
     pub fn interaction(&self, effect: &Effect) -> Option<Effect> {
-        let interactions = self.interactions();
-        interactions.get(effect).copied()
+        TRANSITIONS[*self as usize][*effect as usize]
     }
 
+    /// Mixes this ingredient into a product with the given effects.
+    ///
+    /// - Interactions are looked up on the frozen set of effects the product had before mixing, so an effect created by one interaction never triggers another one.
+    /// - Interactions are applied one at a time in the order of `Effect::ALL`, and replace their effect only if what it turns into isn't part of the product yet, including effects created by earlier interactions.
+    /// - The ingredient's own effect is added last, unless the product already has it or already has `MAX_EFFECTS` effects.
+    ///
+    /// The order only matters for chains, where the effect one interaction turns into is the one another interaction replaces, such as Foggy to Calming and Calming to Slippery for Paracetamol.
+    /// These rules are the calculator's own model of mixing and haven't been checked against the game beyond single recipes.
+    /// In particular the order of `Effect::ALL` is only a fixed convention that keeps results deterministic, the game may resolve chains in another order.
     pub fn apply_to_effect_set(&self, effect_set: &mut EnumSet<Effect>) {
-        let frozen_effect_set = *effect_set;
+        *effect_set = EnumSet::from_repr(self.apply_to_mask(effect_set.as_repr()));
+    }
 
-        if effect_set.len() < 8 {
-            effect_set.insert(self.effect());
+    /// Returns every effect set that turns into `effect_set` when this ingredient is mixed in, in increasing order of their masks.
+    ///
+    /// Mixing never removes an effect, so every effect of the result comes from either the same effect, an effect that turns into it, or nothing for the ingredient's own effect.
+    /// Candidates are built by picking one of those for every effect, and only kept if mixing them gives `effect_set` back.
+    pub fn unapply_to_effect_set(&self, effect_set: EnumSet<Effect>) -> Vec<EnumSet<Effect>> {
+        let sources = &SOURCES[*self as usize];
+        let mut candidates = vec![EnumSet::new()];
+        for effect in effect_set {
+            let options = sources[effect as usize] | effect;
+            let may_be_new = effect == self.effect();
+            candidates = candidates
+                .into_iter()
+                .flat_map(|candidate| {
+                    let picked = (options - candidate).iter().map(move |e| candidate | e);
+                    picked.chain(may_be_new.then_some(candidate))
+                })
+                .collect();
         }
 
-        for (from_effect, to_effect) in self.interactions() {
-            if frozen_effect_set.contains(*from_effect) && !frozen_effect_set.contains(*to_effect) {
-                effect_set.remove(*from_effect);
-                effect_set.insert(*to_effect);
+        candidates.retain(|candidate| {
+            let mut mixed = *candidate;
+            self.apply_to_effect_set(&mut mixed);
+            mixed == effect_set
+        });
+        candidates.sort_unstable_by_key(|candidate| candidate.as_repr());
+        candidates.dedup();
+        candidates
+    }
+
+    /// Mixes this ingredient into a product whose effects are given as a raw mask, as in `apply_to_effect_set`.
+    ///
+    /// This is the hot path of every search, so it only goes through the transition table and bit operations.
+    pub fn apply_to_mask(&self, mask: u64) -> u64 {
+        let transitions = &TRANSITIONS[*self as usize];
+        let mut result = mask;
+        let mut frozen = mask;
+        while frozen != 0 {
+            let from_effect = frozen.trailing_zeros();
+            frozen &= frozen - 1;
+            if let Some(to_effect) = transitions[from_effect as usize] {
+                let to_bit = 1 << to_effect as u64;
+                if result & to_bit == 0 {
+                    result = (result & !(1 << from_effect)) | to_bit;
+                }
             }
         }
+
+        if (result.count_ones() as usize) < MAX_EFFECTS {
+            result |= 1 << self.effect() as u64;
+        }
+
+        result
     }
 
     /// Returns the price of this ingredient as a negative `f32`.
@@ -453,3 +557,11 @@ impl std::fmt::Display for Intermediate {
         write!(f, "{s}")
     }
 }
+
+/// Lowercases `name` and strips everything but letters and digits from it, so that names can be compared loosely.
+pub(crate) fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
@@ -6,19 +6,27 @@ pub mod recipe;
 #[cfg(test)]
 mod tests;
 
-use effect::Effect;
+use effect::{Effect, EffectConstraint, EffectConstraints};
 use enumset::EnumSet;
 use expenses::{Additive, Expenses, PseudoQuality, Soil};
 use iced::{
-    Alignment, Element, Length, Padding, Task, Theme,
+    Alignment, Element, Length, Padding, Subscription, Task, Theme,
+    futures::{SinkExt, Stream, StreamExt, channel::mpsc},
     widget::{
         button, checkbox, column, container, horizontal_space, pick_list, progress_bar, row,
-        scrollable, text, text_editor,
+        scrollable, text, text_editor, text_input,
     },
 };
 use ingredients::{Base, Intermediate};
-use recipe::Recipe;
+use recipe::expression::{Expression, ParseError};
+use recipe::metric::{Metric, Scoring};
 use recipe::search_algorithms::*;
+use recipe::{MAX_EFFECTS, MAX_INGREDIENTS, ParseRecipeError, Recipe, RecipeEdit, StartingState};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum SortType {
@@ -77,9 +85,11 @@ impl SortType {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[expect(clippy::enum_variant_names)]
 enum Mode {
     ReverseCalculator,
     OptimalCalculator,
+    ParetoCalculator,
 }
 
 impl std::fmt::Display for Mode {
@@ -87,6 +97,7 @@ impl std::fmt::Display for Mode {
         let s = match self {
             Mode::ReverseCalculator => String::from("Reverse"),
             Mode::OptimalCalculator => String::from("Optimal"),
+            Mode::ParetoCalculator => String::from("Pareto"),
         };
 
         write!(f, "{s}")
@@ -94,37 +105,228 @@ impl std::fmt::Display for Mode {
 }
 
 impl Mode {
-    pub const ALL: &'static [Self] = &[Mode::ReverseCalculator, Mode::OptimalCalculator];
+    pub const ALL: &'static [Self] = &[
+        Mode::ReverseCalculator,
+        Mode::OptimalCalculator,
+        Mode::ParetoCalculator,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IngredientPreset {
+    Everything,
+    GasMartOnly,
+}
+
+impl std::fmt::Display for IngredientPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            IngredientPreset::Everything => String::from("Everything"),
+            IngredientPreset::GasMartOnly => String::from("Gas-Mart only"),
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl IngredientPreset {
+    pub const ALL: &'static [Self] = &[IngredientPreset::Everything, IngredientPreset::GasMartOnly];
+
+    /// Returns the ingredients included in this `IngredientPreset`.
+    pub fn ingredients(&self) -> EnumSet<Intermediate> {
+        match self {
+            IngredientPreset::Everything => EnumSet::all(),
+            // Addy, Battery and Horse Semen only become available later in a playthrough.
+            IngredientPreset::GasMartOnly => {
+                EnumSet::all()
+                    - (Intermediate::Addy | Intermediate::Battery | Intermediate::HorseSemen)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReverseGoal {
+    Shortest,
+    Cheapest,
+}
+
+impl std::fmt::Display for ReverseGoal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ReverseGoal::Shortest => String::from("Shortest"),
+            ReverseGoal::Cheapest => String::from("Cheapest"),
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl ReverseGoal {
+    pub const ALL: &'static [Self] = &[ReverseGoal::Shortest, ReverseGoal::Cheapest];
+}
+
+/// How the Reverse calculator looks for the shortest recipe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReverseAlgorithm {
+    /// Explores forward from every base until a recipe satisfies the target.
+    Forward,
+    /// Explores forward from every base and backward from the target until the two meet.
+    MeetInTheMiddle,
+}
+
+impl std::fmt::Display for ReverseAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ReverseAlgorithm::Forward => String::from("Forward Search"),
+            ReverseAlgorithm::MeetInTheMiddle => String::from("Meet in the Middle"),
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl ReverseAlgorithm {
+    pub const ALL: &'static [Self] =
+        &[ReverseAlgorithm::Forward, ReverseAlgorithm::MeetInTheMiddle];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OptimalBase {
+    Single(Base),
+    Any,
+}
+
+impl std::fmt::Display for OptimalBase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptimalBase::Single(base) => write!(f, "{base}"),
+            OptimalBase::Any => write!(f, "Any base"),
+        }
+    }
+}
+
+impl OptimalBase {
+    pub const ALL: &'static [Self] = &[
+        OptimalBase::Single(Base::OGKush),
+        OptimalBase::Single(Base::SourDiesel),
+        OptimalBase::Single(Base::GreenCrack),
+        OptimalBase::Single(Base::GranddaddyPurple),
+        OptimalBase::Single(Base::Meth),
+        OptimalBase::Any,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImproveMode {
+    Continue,
+    SingleEdit,
+}
+
+impl std::fmt::Display for ImproveMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ImproveMode::Continue => String::from("Add ingredients"),
+            ImproveMode::SingleEdit => String::from("Insert or replace one"),
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl ImproveMode {
+    pub const ALL: &'static [Self] = &[ImproveMode::Continue, ImproveMode::SingleEdit];
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Metric {
-    ProfitMargin,
-    Profit,
-    SellPrice,
-    ProductionCost,
+enum OptimalAlgorithm {
+    StateSearch,
+    BranchAndBound,
+    BeamSearch,
+    SimulatedAnnealing,
+    TimeBudget,
 }
 
-impl std::fmt::Display for Metric {
+impl std::fmt::Display for OptimalAlgorithm {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s = match self {
-            Metric::ProfitMargin => String::from("Profit Margin"),
-            Metric::Profit => String::from("Profit"),
-            Metric::SellPrice => String::from("Sell Price"),
-            Metric::ProductionCost => String::from("Production Cost"),
+            OptimalAlgorithm::StateSearch => String::from("State Search"),
+            OptimalAlgorithm::BranchAndBound => String::from("Branch and Bound"),
+            OptimalAlgorithm::BeamSearch => String::from("Beam Search"),
+            OptimalAlgorithm::SimulatedAnnealing => String::from("Simulated Annealing"),
+            OptimalAlgorithm::TimeBudget => String::from("Time Budget"),
         };
 
         write!(f, "{s}")
     }
 }
 
-impl Metric {
+impl OptimalAlgorithm {
+    pub const ALL: &'static [Self] = &[
+        OptimalAlgorithm::StateSearch,
+        OptimalAlgorithm::BranchAndBound,
+        OptimalAlgorithm::BeamSearch,
+        OptimalAlgorithm::SimulatedAnnealing,
+        OptimalAlgorithm::TimeBudget,
+    ];
+
+    /// Returns whether this `OptimalAlgorithm` always finds the best recipe, as opposed to a heuristic.
+    pub fn is_exhaustive(&self) -> bool {
+        match self {
+            OptimalAlgorithm::StateSearch | OptimalAlgorithm::BranchAndBound => true,
+            OptimalAlgorithm::BeamSearch
+            | OptimalAlgorithm::SimulatedAnnealing
+            | OptimalAlgorithm::TimeBudget => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Objective {
+    Metric(Metric),
+    Addictiveness,
+    Steps,
+}
+
+impl std::fmt::Display for Objective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Objective::Metric(metric) => write!(f, "{metric}"),
+            Objective::Addictiveness => write!(f, "Addictiveness"),
+            Objective::Steps => write!(f, "Steps"),
+        }
+    }
+}
+
+impl Objective {
     pub const ALL: &'static [Self] = &[
-        Metric::ProductionCost,
-        Metric::SellPrice,
-        Metric::Profit,
-        Metric::ProfitMargin,
+        Objective::Metric(Metric::ProductionCost),
+        Objective::Metric(Metric::SellPrice),
+        Objective::Metric(Metric::Profit),
+        Objective::Metric(Metric::ProfitMargin),
+        Objective::Addictiveness,
+        Objective::Steps,
     ];
+
+    /// Returns the value of this `Objective` for the given `Recipe`, where higher is always better.
+    pub fn value(&self, r: &Recipe, expenses: Expenses) -> f32 {
+        match self {
+            Objective::Metric(metric) => metric.value(r, expenses),
+            Objective::Addictiveness => r.addictiveness(),
+            Objective::Steps => -(r.intermediates().len() as f32),
+        }
+    }
+
+    /// Formats a value returned by `value` for display.
+    pub fn format_value(&self, value: f32) -> String {
+        match self {
+            Objective::Metric(Metric::ProfitMargin) => format!("{:.1}%", 100.0 * value),
+            Objective::Metric(Metric::ProductionCost) => format!("~${:.0}", value.abs()),
+            Objective::Metric(_) => format!("${:.0}", value),
+            Objective::Addictiveness => format!("{:.0}%", 100.0 * value),
+            Objective::Steps => format!("{:.0}", value.abs()),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -132,14 +334,39 @@ enum Message {
     ChangedMode(Mode),
 
     AddedEffect(Effect),
+    ForbadeEffect(Effect),
     RemovedEffect(Effect),
+    ToggledExactEffects(bool),
     UpdatedTargetEffects,
 
     ChangedSortType(SortType),
+    ChangedReverseGoal(ReverseGoal),
+    ChangedReverseAlgorithm(ReverseAlgorithm),
+    ChangedReverseDepth(u8),
+    ToggledBatch(bool),
+    ChangedBatchTargets(text_editor::Action),
 
     ChangedBase(Base),
+    ChangedOptimalBase(OptimalBase),
     ChangedMetric(Metric),
+    ChangedMetricWeight(f32),
+    ChangedAddictivenessWeight(f32),
+    ToggledCustomObjective(bool),
+    ToggledStartRecipe(bool),
+    ChangedStartRecipeText(String),
+    ToggledStartingEffects(bool),
+    ToggledStartingEffect(Effect, bool),
+    UsedActiveRecipeAsStart,
+    ChangedImproveMode(ImproveMode),
+    ChangedObjectiveExpression(String),
     ChangedDepth(u8),
+    ChangedResultCount(usize),
+    ChangedBeamWidth(usize),
+    ChangedAnnealingIterations(u64),
+    ToggledDeterministic(bool),
+    ChangedTimeBudget(u64),
+    ChangedOptimalAlgorithm(OptimalAlgorithm),
+    ToggledObjective(Objective, bool),
 
     ToggledGrowTent(bool),
     #[expect(
@@ -151,100 +378,431 @@ enum Message {
     ToggledFertilizer(bool),
     #[expect(dead_code)]
     ToggledSpeedGrow(bool),
+    ToggledIngredient(Intermediate, bool),
+    ChangedIngredientPreset(IngredientPreset),
+
     ChangedSoil(Soil),
     ChangedPseudo(PseudoQuality),
 
     CalculateRecipe,
+    CancelCalculation,
+    /// Records the statistics of a finished search, or `None` if it was answered by the database, before handling the message carrying its result.
+    SearchFinished(Option<SearchStats>, Box<Message>),
+    ToggledSearchStats,
+    ProgressChannelReady(mpsc::UnboundedSender<SearchProgress>),
+    ProgressUpdated(SearchProgress),
+    ImprovementChannelReady(mpsc::UnboundedSender<Recipe>),
+    FoundBetterRecipe(Recipe),
     CalculateRecipeFinished(Option<Recipe>),
+    CalculateRankedRecipesFinished(Vec<(Recipe, i64)>),
+    SelectedRankedRecipe(usize),
+    CalculateSingleEditsFinished(Vec<(RecipeEdit, Recipe, i64)>),
+    CalculateBaseComparisonFinished(Vec<(Recipe, i64)>),
+    SelectedBaseComparison(usize),
+    CalculateParetoRecipesFinished(Vec<(Recipe, Vec<f32>)>),
+    SelectedParetoRecipe(usize),
+    CalculateBatchFinished(Vec<Vec<TargetRecipes>>),
+    SelectedBatchRecipe(usize, usize),
     ChangedRecipeText(text_editor::Action),
+    LoadedDatabase(Result<Option<Arc<ReachableDatabase>>, String>),
 
     ChangedTheme(Theme),
+    ChangedThreadCount(usize),
+    ToggledLowPriority(bool),
 }
 
+const DEFAULT_OBJECTIVE_EXPRESSION: &str = "profit * 0.7 + addictiveness * 20 - steps * 2";
+
 struct MixCalculator {
     mode: Mode,
 
     // Reverse calculator
     sort_type_selected: Option<SortType>,
-    target_effects: EnumSet<Effect>,
+    effect_constraints: EffectConstraints,
+    reverse_goal_selected: ReverseGoal,
+    reverse_algorithm_selected: ReverseAlgorithm,
+    /// The number of ingredients the Meet in the Middle search goes up to, which can be more than the forward searches manage.
+    reverse_depth_selected: u8,
+    /// Whether the Reverse calculator searches for a whole list of targets instead of the selected effects.
+    batch_enabled: bool,
+    batch_targets_text: text_editor::Content,
+    batch_targets: Result<Vec<EnumSet<Effect>>, String>,
+    /// The targets of the last batch search, each with the recipes found from every base.
+    batch_results: Vec<(EnumSet<Effect>, Vec<TargetRecipes>)>,
+    batch_recipe_selected: Option<(usize, usize)>,
 
     // Optimal calculator
     base_selected: Base,
+    any_base_selected: bool,
     metric_selected: Metric,
+    metric_weight_selected: f32,
+    addictiveness_weight_selected: f32,
+    custom_objective_enabled: bool,
+    start_recipe_enabled: bool,
+    start_recipe_text: String,
+    start_recipe: Result<Recipe, ParseRecipeError>,
+    improve_mode_selected: ImproveMode,
+    /// Whether searches start from an already mixed product with `starting_effects` instead of a fresh base.
+    starting_effects_enabled: bool,
+    starting_effects: EnumSet<Effect>,
+    objective_expression_text: String,
+    objective_expression: Result<Expression, ParseError>,
     depth_selected: u8,
+    result_count_selected: usize,
+    optimal_algorithm_selected: OptimalAlgorithm,
+    beam_width_selected: usize,
+    annealing_iterations_selected: u64,
+    /// Whether searches that depend on randomness or the number of threads always give the same result.
+    deterministic_enabled: bool,
+    /// The time budget of the Time Budget algorithm, in seconds.
+    time_budget_selected: u64,
+    ranked_recipes: Vec<(Recipe, i64)>,
+    ranked_recipe_selected: Option<usize>,
+    /// The edit leading to every ranked recipe, if they were found by improving a starting recipe one ingredient at a time.
+    ranked_recipe_edits: Vec<RecipeEdit>,
+    base_comparison: Vec<(Recipe, i64)>,
+    base_comparison_selected: Option<usize>,
+    /// Whether the last search with a custom objective found recipes, but none that satisfy its `where` condition.
+    condition_unsatisfied: bool,
+
+    // Pareto calculator
+    objectives_selected: Vec<Objective>,
+    pareto_recipes: Vec<(Recipe, Vec<f32>)>,
+    pareto_recipe_selected: Option<usize>,
 
     expenses: Expenses,
+    allowed_ingredients: EnumSet<Intermediate>,
+    /// The precomputed reachable states, used instead of searching whenever they cover the search.
+    database: Option<Arc<ReachableDatabase>>,
+    database_error: Option<String>,
+
+    thread_settings: ThreadSettings,
+    /// The threads searches run on, kept apart from rayon's global pool.
+    search_pool: Arc<rayon::ThreadPool>,
 
     calculating_recipe: bool,
+    cancellation_token: CancellationToken,
     active_recipe: Option<Recipe>,
     recipe_text: text_editor::Content,
 
     progress_state: f32,
+    progress_sender: Option<mpsc::UnboundedSender<SearchProgress>>,
+    improvement_sender: Option<mpsc::UnboundedSender<Recipe>>,
+    search_progress: Option<SearchProgress>,
+    search_stats: Option<SearchStats>,
+    /// Whether the last search was answered by the database, which doesn't record statistics.
+    answered_by_database: bool,
+    search_stats_expanded: bool,
 
     theme: Theme,
 }
 
-impl Default for MixCalculator {
-    fn default() -> Self {
+impl MixCalculator {
+    /// Creates the calculator with its default inputs, running searches on `search_pool`, which was built from `thread_settings`.
+    fn new(thread_settings: ThreadSettings, search_pool: rayon::ThreadPool) -> Self {
         let default_recipe = Recipe::with_base(Base::OGKush)
             .add_intermediate(Intermediate::Paracetamol)
             .add_intermediate(Intermediate::Cuke)
             .add_intermediate(Intermediate::Donut)
             .add_intermediate(Intermediate::Banana);
+        let default_recipe_summary = recipe_summary(&default_recipe);
         Self {
             mode: Mode::ReverseCalculator,
 
             // Reverse calculator
-            target_effects: Effect::Jennerising
-                | Effect::Sneaky
-                | Effect::Gingeritis
-                | Effect::ThoughtProvoking
-                | Effect::CalorieDense,
+            effect_constraints: EffectConstraints::requiring(
+                Effect::Jennerising
+                    | Effect::Sneaky
+                    | Effect::Gingeritis
+                    | Effect::ThoughtProvoking
+                    | Effect::CalorieDense,
+            ),
             sort_type_selected: Some(SortType::AlphabeticalAscending),
+            reverse_goal_selected: ReverseGoal::Shortest,
+            reverse_algorithm_selected: ReverseAlgorithm::Forward,
+            reverse_depth_selected: 8,
+            batch_enabled: false,
+            batch_targets_text: text_editor::Content::new(),
+            batch_targets: Ok(Vec::new()),
+            batch_results: Vec::new(),
+            batch_recipe_selected: None,
 
             // Optimal calculator
             base_selected: Base::OGKush,
+            any_base_selected: false,
             metric_selected: Metric::ProfitMargin,
+            metric_weight_selected: 1.0,
+            addictiveness_weight_selected: 0.0,
+            custom_objective_enabled: false,
+            start_recipe_enabled: false,
+            start_recipe_text: default_recipe_summary.clone(),
+            start_recipe: default_recipe_summary.parse(),
+            improve_mode_selected: ImproveMode::Continue,
+            starting_effects_enabled: false,
+            starting_effects: EnumSet::new(),
+            objective_expression_text: String::from(DEFAULT_OBJECTIVE_EXPRESSION),
+            objective_expression: Expression::parse(DEFAULT_OBJECTIVE_EXPRESSION),
             depth_selected: 4,
+            result_count_selected: 10,
+            optimal_algorithm_selected: OptimalAlgorithm::StateSearch,
+            beam_width_selected: 100,
+            annealing_iterations_selected: 100_000,
+            deterministic_enabled: true,
+            time_budget_selected: 10,
+            ranked_recipes: Vec::new(),
+            ranked_recipe_selected: None,
+            ranked_recipe_edits: Vec::new(),
+            base_comparison: Vec::new(),
+            base_comparison_selected: None,
+            condition_unsatisfied: false,
+
+            // Pareto calculator
+            objectives_selected: vec![Objective::Metric(Metric::Profit), Objective::Steps],
+            pareto_recipes: Vec::new(),
+            pareto_recipe_selected: None,
 
             expenses: Expenses::default(),
+            allowed_ingredients: IngredientPreset::Everything.ingredients(),
+            database: None,
+            database_error: None,
+
+            thread_settings,
+            search_pool: Arc::new(search_pool),
 
             calculating_recipe: false,
+            cancellation_token: CancellationToken::new(),
             recipe_text: text_editor::Content::with_text(&default_recipe.to_string()),
             active_recipe: Some(default_recipe),
 
             progress_state: Default::default(),
+            progress_sender: None,
+            improvement_sender: None,
+            search_progress: None,
+            search_stats: None,
+            answered_by_database: false,
+            search_stats_expanded: false,
 
             theme: Theme::Nightfly,
         }
     }
-}
 
-impl MixCalculator {
     fn total_addictiveness(&self) -> f32 {
-        effect::get_total_addictiveness(self.target_effects)
+        effect::get_total_addictiveness(self.effect_constraints.required)
     }
 
     fn total_price_modifier(&self) -> f32 {
-        effect::get_total_price_modifier(self.target_effects)
+        effect::get_total_price_modifier(self.effect_constraints.required)
+    }
+
+    /// Returns the `Scoring` built from the Optimal calculator's metric and weights.
+    fn scoring(&self) -> Scoring {
+        Scoring {
+            metric: self.metric_selected,
+            metric_weight: self.metric_weight_selected,
+            addictiveness_weight: self.addictiveness_weight_selected,
+        }
+    }
+
+    /// Returns the function ranking recipes in the Optimal calculator, in hundredths of the score shown to the user.
+    fn optimal_objective(&self) -> impl Fn(&Recipe) -> i64 + Copy + Send + Sync + 'static {
+        let scoring = self.scoring();
+        let expenses = self.expenses;
+        move |r: &Recipe| (100.0 * scoring.value(r, expenses)).round() as i64
+    }
+
+    /// Returns the function giving the state a mix of a base starts from, which has the custom starting effects if they are enabled.
+    fn start_of(&self) -> impl Fn(Base) -> StartingState + Copy + Send + Sync + 'static {
+        let effects = self
+            .starting_effects_enabled
+            .then_some(self.starting_effects);
+        move |base: Base| match effects {
+            Some(effects) => StartingState { base, effects },
+            None => StartingState::from(base),
+        }
+    }
+
+    /// Returns the recipe the Optimal calculator starts searching from.
+    fn optimal_root(&self) -> Recipe {
+        match (&self.start_recipe, self.start_recipe_enabled) {
+            (Ok(r), true) => r.clone(),
+            _ => Recipe::with_start(self.start_of()(self.base_selected)),
+        }
+    }
+
+    /// Returns how searches pick between equally good recipes.
+    fn tie_break(&self) -> TieBreak {
+        match self.deterministic_enabled {
+            true => TieBreak::Deterministic,
+            false => TieBreak::Any,
+        }
+    }
+
+    /// Returns the seed of the next Simulated Annealing search, which is fixed if results have to be deterministic.
+    fn annealing_seed(&self) -> u64 {
+        match self.deterministic_enabled {
+            true => 0,
+            false => fastrand::u64(..),
+        }
+    }
+
+    /// Returns the precomputed database if it can answer a search with at most `depth` ingredients, which requires fresh bases and every ingredient to be allowed.
+    fn usable_database(&self, depth: i8) -> Option<Arc<ReachableDatabase>> {
+        self.database.clone().filter(|db| {
+            db.depth() >= depth
+                && !self.starting_effects_enabled
+                && self.allowed_ingredients == EnumSet::all()
+        })
+    }
+
+    /// Returns the recipe of the last batch search for the target at `target` from the base at `start`, picked by the selected goal.
+    fn batch_recipe(&self, target: usize, start: usize) -> Option<&Recipe> {
+        let recipes = self.batch_results.get(target)?.1.get(start)?;
+        match self.reverse_goal_selected {
+            ReverseGoal::Shortest => recipes.shortest.as_ref(),
+            ReverseGoal::Cheapest => recipes.cheapest.as_ref(),
+        }
+    }
+
+    /// Returns the position of the base with the best recipe of the last batch search for the target at `target`, according to the selected goal.
+    fn best_batch_recipe(&self, target: usize) -> Option<usize> {
+        let starts = self.batch_results.get(target)?.1.len();
+        let cost = |r: &Recipe| -r.production_cost(self.expenses);
+        (0..starts)
+            .filter_map(|start| Some((start, self.batch_recipe(target, start)?)))
+            .min_by(|(_, a), (_, b)| match self.reverse_goal_selected {
+                ReverseGoal::Shortest => a
+                    .intermediates()
+                    .len()
+                    .cmp(&b.intermediates().len())
+                    .then(cost(a).total_cmp(&cost(b))),
+                ReverseGoal::Cheapest => cost(a)
+                    .total_cmp(&cost(b))
+                    .then(a.intermediates().len().cmp(&b.intermediates().len())),
+            })
+            .map(|(start, _)| start)
+    }
+
+    /// Removes the results that don't satisfy the `where` condition of the custom objective, if it is enabled, and records whether that left none.
+    fn drop_rejected<T>(&mut self, results: &mut Vec<T>, recipe: impl Fn(&T) -> &Recipe) {
+        let Ok(expression) = &self.objective_expression else {
+            return;
+        };
+        if !self.custom_objective_enabled {
+            return;
+        }
+
+        let found = !results.is_empty();
+        results.retain(|result| expression.accepts(recipe(result), self.expenses));
+        self.condition_unsatisfied = found && results.is_empty();
+    }
+
+    /// Returns the text shown in place of an empty list of results.
+    fn no_results_text(&self) -> &'static str {
+        match self.condition_unsatisfied {
+            true => "No recipe satisfies the condition!",
+            false => "No recipes found!",
+        }
+    }
+
+    /// Returns whether the Optimal calculator searches from every base instead of a single root.
+    fn searches_every_base(&self) -> bool {
+        self.any_base_selected && !self.start_recipe_enabled
+    }
+
+    /// Returns the highest depth the current mode and algorithm can search in reasonable time, without growing the starting recipe past `MAX_INGREDIENTS`.
+    fn max_depth(&self) -> u8 {
+        let reasonable = match self.mode == Mode::OptimalCalculator
+            && !self.custom_objective_enabled
+            && !self.optimal_algorithm_selected.is_exhaustive()
+        {
+            true => 20,
+            false => 8,
+        };
+        let room = match self.mode {
+            Mode::OptimalCalculator => {
+                MAX_INGREDIENTS.saturating_sub(self.optimal_root().intermediates().len())
+            }
+            _ => MAX_INGREDIENTS,
+        };
+        reasonable.min(room as u8)
     }
 
     fn theme(&self) -> Theme {
         self.theme.clone()
     }
 
+    /// Marks a search as running and returns a fresh `CancellationToken` and `ProgressReporter` for it.
+    fn start_calculation(&mut self) -> (CancellationToken, ProgressReporter) {
+        self.calculating_recipe = true;
+        self.condition_unsatisfied = false;
+        self.cancellation_token = CancellationToken::new();
+        self.progress_state = 0.0;
+        self.search_progress = None;
+
+        let progress = match self.progress_sender.clone() {
+            Some(sender) => ProgressReporter::with_callback(move |p| {
+                let _ = sender.unbounded_send(p);
+            }),
+            None => ProgressReporter::new(),
+        };
+
+        (self.cancellation_token.clone(), progress)
+    }
+
+    /// Replaces the pool searches run on with one built from `settings`, keeping the current one if the threads can't be spawned.
+    ///
+    /// A running search keeps its pool until it finishes.
+    fn set_thread_settings(&mut self, settings: ThreadSettings) {
+        if let Ok(pool) = settings.build_pool() {
+            self.thread_settings = settings;
+            self.search_pool = Arc::new(pool);
+        }
+    }
+
+    /// Runs `search` on the search pool without blocking the interface, and turns its result into a message.
+    fn perform_search<T>(
+        &self,
+        search: impl FnOnce() -> T + Send + 'static,
+        message: impl Fn(T) -> Message + Send + 'static,
+    ) -> Task<Message>
+    where
+        T: Send + 'static,
+    {
+        let pool = self.search_pool.clone();
+        Task::perform(async move { pool.install(search) }, message)
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        Subscription::batch([
+            Subscription::run(progress_worker),
+            Subscription::run(improvement_worker),
+        ])
+    }
+
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::ChangedMode(mode) => {
                 self.mode = mode;
+                self.depth_selected = self.depth_selected.min(self.max_depth());
                 Task::none()
             }
             Message::AddedEffect(effect) => {
-                self.target_effects.insert(effect);
+                self.effect_constraints
+                    .set(effect, EffectConstraint::Required);
+                self.update(Message::UpdatedTargetEffects)
+            }
+            Message::ForbadeEffect(effect) => {
+                self.effect_constraints
+                    .set(effect, EffectConstraint::Forbidden);
                 self.update(Message::UpdatedTargetEffects)
             }
             Message::RemovedEffect(effect) => {
-                self.target_effects.remove(effect);
+                self.effect_constraints
+                    .set(effect, EffectConstraint::DontCare);
+                self.update(Message::UpdatedTargetEffects)
+            }
+            Message::ToggledExactEffects(exact) => {
+                self.effect_constraints.exact = exact;
                 self.update(Message::UpdatedTargetEffects)
             }
             Message::UpdatedTargetEffects => Task::none(),
@@ -252,18 +810,139 @@ impl MixCalculator {
                 self.sort_type_selected = Some(sort_type);
                 Task::none()
             }
+            Message::ChangedReverseGoal(goal) => {
+                self.reverse_goal_selected = goal;
+                match self.batch_recipe_selected {
+                    Some((target, start)) if self.batch_enabled => {
+                        self.update(Message::SelectedBatchRecipe(target, start))
+                    }
+                    _ => Task::none(),
+                }
+            }
+            Message::ChangedReverseAlgorithm(algorithm) => {
+                self.reverse_algorithm_selected = algorithm;
+                Task::none()
+            }
+            Message::ChangedReverseDepth(depth) => {
+                self.reverse_depth_selected = depth;
+                Task::none()
+            }
+            Message::ToggledBatch(b) => {
+                self.batch_enabled = b;
+                Task::none()
+            }
+            Message::ChangedBatchTargets(action) => {
+                self.batch_targets_text.perform(action);
+                self.batch_targets = parse_targets(&self.batch_targets_text.text());
+                Task::none()
+            }
             Message::ChangedBase(base) => {
                 self.base_selected = base;
                 Task::none()
             }
+            Message::ChangedOptimalBase(OptimalBase::Single(base)) => {
+                self.base_selected = base;
+                self.any_base_selected = false;
+                Task::none()
+            }
+            Message::ChangedOptimalBase(OptimalBase::Any) => {
+                self.any_base_selected = true;
+                Task::none()
+            }
             Message::ChangedMetric(metric) => {
                 self.metric_selected = metric;
                 Task::none()
             }
+            Message::ChangedMetricWeight(weight) => {
+                self.metric_weight_selected = weight;
+                Task::none()
+            }
+            Message::ChangedAddictivenessWeight(weight) => {
+                self.addictiveness_weight_selected = weight;
+                Task::none()
+            }
+            Message::ToggledCustomObjective(b) => {
+                self.custom_objective_enabled = b;
+                self.depth_selected = self.depth_selected.min(self.max_depth());
+                Task::none()
+            }
+            Message::ChangedObjectiveExpression(source) => {
+                self.objective_expression = Expression::parse(&source);
+                self.objective_expression_text = source;
+                Task::none()
+            }
+            Message::ToggledStartRecipe(b) => {
+                self.start_recipe_enabled = b;
+                self.depth_selected = self.depth_selected.min(self.max_depth());
+                Task::none()
+            }
+            Message::ToggledStartingEffects(b) => {
+                self.starting_effects_enabled = b;
+                Task::none()
+            }
+            Message::ToggledStartingEffect(effect, true) => {
+                if self.starting_effects.len() < MAX_EFFECTS {
+                    self.starting_effects.insert(effect);
+                }
+                Task::none()
+            }
+            Message::ToggledStartingEffect(effect, false) => {
+                self.starting_effects.remove(effect);
+                Task::none()
+            }
+            Message::ChangedStartRecipeText(text) => {
+                self.start_recipe = text.parse();
+                self.start_recipe_text = text;
+                self.depth_selected = self.depth_selected.min(self.max_depth());
+                Task::none()
+            }
+            Message::UsedActiveRecipeAsStart => {
+                let r = self.active_recipe.clone().unwrap_or_default();
+                self.update(Message::ChangedStartRecipeText(recipe_summary(&r)))
+            }
+            Message::ChangedImproveMode(mode) => {
+                self.improve_mode_selected = mode;
+                Task::none()
+            }
             Message::ChangedDepth(depth) => {
                 self.depth_selected = depth;
                 Task::none()
             }
+            Message::ChangedResultCount(count) => {
+                self.result_count_selected = count;
+                Task::none()
+            }
+            Message::ChangedOptimalAlgorithm(algorithm) => {
+                self.optimal_algorithm_selected = algorithm;
+                self.depth_selected = self.depth_selected.min(self.max_depth());
+                Task::none()
+            }
+            Message::ChangedBeamWidth(width) => {
+                self.beam_width_selected = width;
+                Task::none()
+            }
+            Message::ChangedAnnealingIterations(iterations) => {
+                self.annealing_iterations_selected = iterations;
+                Task::none()
+            }
+            Message::ToggledDeterministic(b) => {
+                self.deterministic_enabled = b;
+                Task::none()
+            }
+            Message::ChangedTimeBudget(seconds) => {
+                self.time_budget_selected = seconds;
+                Task::none()
+            }
+            Message::ToggledObjective(objective, true) => {
+                if !self.objectives_selected.contains(&objective) {
+                    self.objectives_selected.push(objective);
+                }
+                Task::none()
+            }
+            Message::ToggledObjective(objective, false) => {
+                self.objectives_selected.retain(|o| *o != objective);
+                Task::none()
+            }
             Message::ToggledGrowTent(b) => {
                 self.expenses.grow_tent = b;
                 Task::none()
@@ -280,6 +959,18 @@ impl MixCalculator {
                 self.expenses.additives ^= Additive::SpeedGrow;
                 Task::none()
             }
+            Message::ToggledIngredient(ingredient, true) => {
+                self.allowed_ingredients.insert(ingredient);
+                Task::none()
+            }
+            Message::ToggledIngredient(ingredient, false) => {
+                self.allowed_ingredients.remove(ingredient);
+                Task::none()
+            }
+            Message::ChangedIngredientPreset(preset) => {
+                self.allowed_ingredients = preset.ingredients();
+                Task::none()
+            }
             Message::ChangedSoil(soil) => {
                 self.expenses.soil = soil;
                 Task::none()
@@ -292,49 +983,453 @@ impl MixCalculator {
                 self.theme = theme;
                 Task::none()
             }
-            Message::CalculateRecipe if self.mode == Mode::OptimalCalculator => {
-                self.calculating_recipe = true;
-
-                let root = Recipe::with_base(self.base_selected);
-                let f = match self.metric_selected {
-                    Metric::ProfitMargin => |r: &Recipe| {
-                        (100.0 * r.addictiveness()) as i64
-                            + (100.0 * r.profit_margin(Expenses::default())) as i64
-                    },
-                    Metric::Profit => |r: &Recipe| {
-                        (100.0 * r.addictiveness()) as i64
-                            + (100.0 * r.profit(Expenses::default())) as i64
+            Message::ChangedThreadCount(threads) => {
+                self.set_thread_settings(ThreadSettings {
+                    threads,
+                    ..self.thread_settings
+                });
+                Task::none()
+            }
+            Message::ToggledLowPriority(low_priority) => {
+                self.set_thread_settings(ThreadSettings {
+                    low_priority,
+                    ..self.thread_settings
+                });
+                Task::none()
+            }
+            Message::CalculateRecipe
+                if self.mode == Mode::OptimalCalculator
+                    && self.start_recipe_enabled
+                    && self.improve_mode_selected == ImproveMode::SingleEdit =>
+            {
+                let Ok(root) = self.start_recipe.clone() else {
+                    return Task::none();
+                };
+                let f: Arc<dyn Fn(&Recipe) -> i64 + Send + Sync> = match (
+                    self.custom_objective_enabled,
+                    self.objective_expression.clone(),
+                ) {
+                    (true, Ok(expression)) => {
+                        let expenses = self.expenses;
+                        Arc::new(move |r: &Recipe| {
+                            (100.0 * expression.value(r, expenses)).round() as i64
+                        })
+                    }
+                    (true, Err(_)) => return Task::none(),
+                    (false, _) => Arc::new(self.optimal_objective()),
+                };
+                let (cancel, progress) = self.start_calculation();
+
+                let k = self.result_count_selected;
+                let allowed = self.allowed_ingredients;
+                self.perform_search(
+                    move || {
+                        search_for_recipes_top_k_single_edit(
+                            root,
+                            |r| f(r),
+                            allowed,
+                            k,
+                            &cancel,
+                            &progress,
+                        )
                     },
-                    Metric::SellPrice => |r: &Recipe| {
-                        (100.0 * r.addictiveness()) as i64 + (100.0 * r.sell_price()) as i64
+                    with_stats(Message::CalculateSingleEditsFinished),
+                )
+            }
+            Message::CalculateRecipe
+                if self.mode == Mode::OptimalCalculator && self.custom_objective_enabled =>
+            {
+                let Ok(expression) = self.objective_expression.clone() else {
+                    return Task::none();
+                };
+                let (cancel, progress) = self.start_calculation();
+
+                let root = self.optimal_root();
+                let expenses = self.expenses;
+                let f = move |r: &Recipe| (100.0 * expression.value(r, expenses)).round() as i64;
+                let depth = self.depth_selected as i8;
+                let allowed = self.allowed_ingredients;
+                let start_of = self.start_of();
+                match self.searches_every_base() {
+                    true => self.perform_search(
+                        move || {
+                            let (results, stats) = search_for_recipes_every_base(
+                                |root, cancel, progress| {
+                                    let root = Recipe::with_start(start_of(root.base()));
+                                    let (r, _) = search_for_recipe_max_dfs(
+                                        root, &f, depth, allowed, cancel, progress,
+                                    );
+                                    let score = f(&r);
+                                    (r, score)
+                                },
+                                &cancel,
+                                &progress,
+                            );
+                            let results = results.into_iter().map(|(_, result)| result);
+                            (results.collect(), stats)
+                        },
+                        with_stats(Message::CalculateBaseComparisonFinished),
+                    ),
+                    false => self.perform_search(
+                        move || {
+                            let (r, stats) = search_for_recipe_max_dfs(
+                                root, &f, depth, allowed, &cancel, &progress,
+                            );
+                            let score = f(&r);
+                            (vec![(r, score)], stats)
+                        },
+                        with_stats(Message::CalculateRankedRecipesFinished),
+                    ),
+                }
+            }
+            Message::CalculateRecipe
+                if self.mode == Mode::OptimalCalculator && self.searches_every_base() =>
+            {
+                let (cancel, progress) = self.start_calculation();
+
+                let algorithm = self.optimal_algorithm_selected;
+                let f = self.optimal_objective();
+                let scoring = self.scoring();
+                let expenses = self.expenses;
+                let depth = self.depth_selected as i8;
+                let allowed = self.allowed_ingredients;
+                let width = self.beam_width_selected;
+                let iterations = self.annealing_iterations_selected;
+                let seed = self.annealing_seed();
+                let tie_break = self.tie_break();
+                let budget = Duration::from_secs(self.time_budget_selected);
+                let start_of = self.start_of();
+                let database = self.usable_database(depth);
+                self.perform_search(
+                    move || {
+                        let (results, stats) = search_for_recipes_every_base(
+                            |root, cancel, progress| {
+                                let root = Recipe::with_start(start_of(root.base()));
+                                let r = match (algorithm, &database) {
+                                    (OptimalAlgorithm::StateSearch, Some(database)) => {
+                                        database.max(root.base(), f, depth)
+                                    }
+                                    (OptimalAlgorithm::StateSearch, None) => {
+                                        search_for_recipe_max_bfs(
+                                            root, f, depth, allowed, cancel, progress,
+                                        )
+                                        .0
+                                    }
+                                    (OptimalAlgorithm::BranchAndBound, _) => {
+                                        search_for_recipe_max_branch_and_bound(
+                                            root, scoring, expenses, depth, allowed, cancel,
+                                            progress,
+                                        )
+                                        .0
+                                    }
+                                    (OptimalAlgorithm::BeamSearch, _) => {
+                                        search_for_recipe_max_beam(
+                                            root, f, depth, allowed, width, cancel, progress,
+                                        )
+                                        .0
+                                    }
+                                    (OptimalAlgorithm::SimulatedAnnealing, _) => {
+                                        search_for_recipe_max_annealing(
+                                            root,
+                                            |r| scoring.value(r, expenses),
+                                            depth,
+                                            allowed,
+                                            iterations,
+                                            seed,
+                                            tie_break,
+                                            cancel,
+                                            progress,
+                                        )
+                                        .0
+                                    }
+                                    (OptimalAlgorithm::TimeBudget, _) => {
+                                        search_for_recipe_max_anytime(
+                                            root,
+                                            scoring,
+                                            expenses,
+                                            budget,
+                                            allowed,
+                                            |_| {},
+                                            cancel,
+                                            progress,
+                                        )
+                                        .0
+                                    }
+                                };
+                                let score = f(&r);
+                                (r, score)
+                            },
+                            &cancel,
+                            &progress,
+                        );
+                        let results = results.into_iter().map(|(_, result)| result);
+                        (results.collect(), stats)
                     },
-                    Metric::ProductionCost => |r: &Recipe| {
-                        (100.0 * r.addictiveness()) as i64
-                            + (100.0 * r.production_cost(Expenses::default())) as i64
+                    with_stats(Message::CalculateBaseComparisonFinished),
+                )
+            }
+            Message::CalculateRecipe
+                if self.mode == Mode::OptimalCalculator
+                    && !self.searches_every_base()
+                    && self.optimal_algorithm_selected == OptimalAlgorithm::TimeBudget =>
+            {
+                let (cancel, progress) = self.start_calculation();
+
+                let root = self.optimal_root();
+                let f = self.optimal_objective();
+                let scoring = self.scoring();
+                let expenses = self.expenses;
+                let budget = Duration::from_secs(self.time_budget_selected);
+                let allowed = self.allowed_ingredients;
+                let improvement_sender = self.improvement_sender.clone();
+                self.perform_search(
+                    move || {
+                        let (r, stats) = search_for_recipe_max_anytime(
+                            root,
+                            scoring,
+                            expenses,
+                            budget,
+                            allowed,
+                            |r| {
+                                if let Some(sender) = &improvement_sender {
+                                    let _ = sender.unbounded_send(r.clone());
+                                }
+                            },
+                            &cancel,
+                            &progress,
+                        );
+                        let score = f(&r);
+                        (vec![(r, score)], stats)
                     },
-                };
+                    with_stats(Message::CalculateRankedRecipesFinished),
+                )
+            }
+            Message::CalculateRecipe
+                if self.mode == Mode::OptimalCalculator
+                    && !self.optimal_algorithm_selected.is_exhaustive() =>
+            {
+                let (cancel, progress) = self.start_calculation();
+
+                let root = self.optimal_root();
+                let algorithm = self.optimal_algorithm_selected;
+                let f = self.optimal_objective();
+                let scoring = self.scoring();
+                let expenses = self.expenses;
                 let depth = self.depth_selected as i8;
-                Task::perform(
-                    async move { search_for_recipe_max_dfs(root, f, depth) },
-                    |r| Message::CalculateRecipeFinished(Some(r)),
+                let allowed = self.allowed_ingredients;
+                let width = self.beam_width_selected;
+                let iterations = self.annealing_iterations_selected;
+                let seed = self.annealing_seed();
+                let tie_break = self.tie_break();
+                self.perform_search(
+                    move || {
+                        let (r, stats) = match algorithm {
+                            OptimalAlgorithm::SimulatedAnnealing => {
+                                search_for_recipe_max_annealing(
+                                    root,
+                                    |r| scoring.value(r, expenses),
+                                    depth,
+                                    allowed,
+                                    iterations,
+                                    seed,
+                                    tie_break,
+                                    &cancel,
+                                    &progress,
+                                )
+                            }
+                            _ => search_for_recipe_max_beam(
+                                root, f, depth, allowed, width, &cancel, &progress,
+                            ),
+                        };
+                        let score = f(&r);
+                        (vec![(r, score)], stats)
+                    },
+                    with_stats(Message::CalculateRankedRecipesFinished),
                 )
             }
-            Message::CalculateRecipe => {
-                self.calculating_recipe = true;
-                let target_effects = self.target_effects;
-                Task::perform(
-                    async move {
-                        search_for_recipe_find_iddfs(
-                            move |r| target_effects.is_subset(r.calculate_effects()),
-                            8,
-                        )
+            Message::CalculateRecipe
+                if self.mode == Mode::OptimalCalculator
+                    && self.optimal_algorithm_selected == OptimalAlgorithm::BranchAndBound =>
+            {
+                let (cancel, progress) = self.start_calculation();
+
+                let root = self.optimal_root();
+                let f = self.optimal_objective();
+                let scoring = self.scoring();
+                let expenses = self.expenses;
+                let depth = self.depth_selected as i8;
+                let allowed = self.allowed_ingredients;
+                self.perform_search(
+                    move || {
+                        let (r, stats) = search_for_recipe_max_branch_and_bound(
+                            root, scoring, expenses, depth, allowed, &cancel, &progress,
+                        );
+                        let score = f(&r);
+                        (vec![(r, score)], stats)
                     },
-                    Message::CalculateRecipeFinished,
+                    with_stats(Message::CalculateRankedRecipesFinished),
                 )
             }
-            Message::CalculateRecipeFinished(recipe) => {
-                self.calculating_recipe = false;
-                match recipe {
+            Message::CalculateRecipe if self.mode == Mode::OptimalCalculator => {
+                let (cancel, progress) = self.start_calculation();
+
+                let root = self.optimal_root();
+                let f = self.optimal_objective();
+                let depth = self.depth_selected as i8;
+                let k = self.result_count_selected;
+                let allowed = self.allowed_ingredients;
+                let database = self
+                    .usable_database(depth)
+                    .filter(|_| !self.start_recipe_enabled);
+                self.perform_search(
+                    move || match database {
+                        Some(database) => (database.top_k(root.base(), f, depth, k), None),
+                        None => {
+                            let (recipes, stats) = search_for_recipes_top_k_bfs(
+                                root, f, depth, allowed, k, &cancel, &progress,
+                            );
+                            (recipes, Some(stats))
+                        }
+                    },
+                    with_stats(Message::CalculateRankedRecipesFinished),
+                )
+            }
+            Message::CalculateRecipe if self.mode == Mode::ParetoCalculator => {
+                let (cancel, progress) = self.start_calculation();
+
+                let root = Recipe::with_start(self.start_of()(self.base_selected));
+                let objectives = self.objectives_selected.clone();
+                let expenses = self.expenses;
+                let depth = self.depth_selected as i8;
+                let allowed = self.allowed_ingredients;
+                self.perform_search(
+                    move || {
+                        search_for_recipes_pareto_bfs(
+                            root,
+                            move |r| objectives.iter().map(|o| o.value(r, expenses)).collect(),
+                            depth,
+                            allowed,
+                            &cancel,
+                            &progress,
+                        )
+                    },
+                    with_stats(Message::CalculateParetoRecipesFinished),
+                )
+            }
+            Message::CalculateRecipe if self.batch_enabled => {
+                let Ok(targets) = self.batch_targets.clone() else {
+                    return Task::none();
+                };
+                let (cancel, progress) = self.start_calculation();
+
+                let constraints: Vec<_> = targets
+                    .iter()
+                    .map(|t| EffectConstraints::requiring(*t))
+                    .collect();
+                let allowed = self.allowed_ingredients;
+                let starts: Vec<_> = Base::ALL.iter().map(|b| self.start_of()(*b)).collect();
+                self.batch_results = targets.into_iter().map(|t| (t, Vec::new())).collect();
+                self.perform_search(
+                    move || {
+                        search_for_recipes_batch_bfs(
+                            &starts,
+                            &constraints,
+                            8,
+                            allowed,
+                            &cancel,
+                            &progress,
+                        )
+                    },
+                    with_stats(Message::CalculateBatchFinished),
+                )
+            }
+            Message::CalculateRecipe => {
+                let (cancel, progress) = self.start_calculation();
+                let constraints = self.effect_constraints;
+                let f = move |r: &Recipe| constraints.is_satisfied_by(r.calculate_effects());
+                let expenses = self.expenses;
+                let goal = self.reverse_goal_selected;
+                let algorithm = self.reverse_algorithm_selected;
+                let bidirectional_depth = self.reverse_depth_selected as i8;
+                let allowed = self.allowed_ingredients;
+                let starts: Vec<_> = Base::ALL.iter().map(|b| self.start_of()(*b)).collect();
+                let database = self.usable_database(8);
+                self.perform_search(
+                    move || {
+                        let f_effects = move |_, e| constraints.is_satisfied_by(e);
+                        match (goal, database) {
+                            (ReverseGoal::Shortest, _)
+                                if algorithm == ReverseAlgorithm::MeetInTheMiddle =>
+                            {
+                                let (recipe, stats) = search_for_recipe_find_bidirectional(
+                                    &starts,
+                                    constraints,
+                                    bidirectional_depth,
+                                    allowed,
+                                    &cancel,
+                                    &progress,
+                                );
+                                (recipe, Some(stats))
+                            }
+                            (ReverseGoal::Shortest, Some(database)) => {
+                                (database.find_shortest(f_effects, 8), None)
+                            }
+                            (ReverseGoal::Cheapest, Some(database)) => {
+                                (database.find_cheapest(f_effects, expenses, 8), None)
+                            }
+                            (ReverseGoal::Shortest, None) => {
+                                let (recipe, stats) = search_for_recipe_find_bfs(
+                                    &starts, f, 8, allowed, &cancel, &progress,
+                                );
+                                (recipe, Some(stats))
+                            }
+                            (ReverseGoal::Cheapest, None) => {
+                                let (recipe, stats) = search_for_recipe_find_cheapest_ucs(
+                                    &starts, f, expenses, 8, allowed, &cancel, &progress,
+                                );
+                                (recipe, Some(stats))
+                            }
+                        }
+                    },
+                    with_stats(Message::CalculateRecipeFinished),
+                )
+            }
+            Message::CancelCalculation => {
+                self.cancellation_token.cancel();
+                Task::none()
+            }
+            Message::ProgressChannelReady(sender) => {
+                self.progress_sender = Some(sender);
+                Task::none()
+            }
+            Message::ImprovementChannelReady(sender) => {
+                self.improvement_sender = Some(sender);
+                Task::none()
+            }
+            Message::FoundBetterRecipe(recipe) => {
+                // Improvements can still arrive after the search has finished, the final result wins then.
+                if self.calculating_recipe {
+                    let score = self.optimal_objective()(&recipe);
+                    self.recipe_text = text_editor::Content::with_text(&recipe.to_string());
+                    self.ranked_recipes = vec![(recipe.clone(), score)];
+                    self.ranked_recipe_edits.clear();
+                    self.ranked_recipe_selected = Some(0);
+                    self.active_recipe = Some(recipe);
+                }
+                Task::none()
+            }
+            Message::ProgressUpdated(progress) => {
+                if self.calculating_recipe {
+                    self.progress_state = progress.fraction;
+                    self.search_progress = Some(progress);
+                }
+                Task::none()
+            }
+            Message::CalculateRecipeFinished(recipe) => {
+                self.calculating_recipe = false;
+                self.progress_state = 0.0;
+                self.search_progress = None;
+                match recipe {
                     Some(r) => {
                         self.recipe_text = text_editor::Content::with_text(&r.to_string());
                         self.active_recipe = Some(r);
@@ -346,18 +1441,98 @@ impl MixCalculator {
                 }
                 Task::none()
             }
+            Message::SearchFinished(stats, result) => {
+                self.answered_by_database = stats.is_none();
+                self.search_stats = stats;
+                self.update(*result)
+            }
+            Message::ToggledSearchStats => {
+                self.search_stats_expanded = !self.search_stats_expanded;
+                Task::none()
+            }
+            Message::CalculateSingleEditsFinished(mut edits) => {
+                self.drop_rejected(&mut edits, |(_, r, _)| r);
+                (self.ranked_recipe_edits, self.ranked_recipes) = edits
+                    .into_iter()
+                    .map(|(edit, r, score)| (edit, (r, score)))
+                    .unzip();
+                self.update(Message::SelectedRankedRecipe(0))
+            }
+            Message::CalculateRankedRecipesFinished(mut recipes) => {
+                self.drop_rejected(&mut recipes, |(r, _)| r);
+                self.ranked_recipe_edits.clear();
+                self.ranked_recipes = recipes;
+                self.update(Message::SelectedRankedRecipe(0))
+            }
+            Message::SelectedRankedRecipe(index) => {
+                self.ranked_recipe_selected = Some(index);
+                let recipe = self.ranked_recipes.get(index).map(|(r, _)| r.clone());
+                self.update(Message::CalculateRecipeFinished(recipe))
+            }
+            Message::CalculateBaseComparisonFinished(mut recipes) => {
+                self.drop_rejected(&mut recipes, |(r, _)| r);
+                let winner = recipes
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, (_, score))| *score)
+                    .map(|(index, _)| index);
+                self.base_comparison = recipes;
+                match winner {
+                    Some(index) => self.update(Message::SelectedBaseComparison(index)),
+                    None => self.update(Message::CalculateRecipeFinished(None)),
+                }
+            }
+            Message::SelectedBaseComparison(index) => {
+                self.base_comparison_selected = Some(index);
+                let recipe = self.base_comparison.get(index).map(|(r, _)| r.clone());
+                self.update(Message::CalculateRecipeFinished(recipe))
+            }
+            Message::CalculateParetoRecipesFinished(recipes) => {
+                self.pareto_recipes = recipes;
+                self.update(Message::SelectedParetoRecipe(0))
+            }
+            Message::SelectedParetoRecipe(index) => {
+                self.pareto_recipe_selected = Some(index);
+                let recipe = self.pareto_recipes.get(index).map(|(r, _)| r.clone());
+                self.update(Message::CalculateRecipeFinished(recipe))
+            }
+            Message::CalculateBatchFinished(results) => {
+                for ((_, recipes), found) in self.batch_results.iter_mut().zip(results) {
+                    *recipes = found;
+                }
+                match self.best_batch_recipe(0) {
+                    Some(start) => self.update(Message::SelectedBatchRecipe(0, start)),
+                    None => {
+                        self.batch_recipe_selected = None;
+                        self.update(Message::CalculateRecipeFinished(None))
+                    }
+                }
+            }
+            Message::SelectedBatchRecipe(target, start) => {
+                self.batch_recipe_selected = Some((target, start));
+                let recipe = self.batch_recipe(target, start).cloned();
+                self.update(Message::CalculateRecipeFinished(recipe))
+            }
             Message::ChangedRecipeText(action) if !action.is_edit() => {
                 self.recipe_text.perform(action);
                 Task::none()
             }
             Message::ChangedRecipeText(_) => Task::none(),
+            Message::LoadedDatabase(database) => {
+                match database {
+                    Ok(database) => self.database = database,
+                    Err(e) => self.database_error = Some(e),
+                }
+                Task::none()
+            }
         }
     }
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         let toolbar = self.toolbar();
         let body = match self.mode {
             Mode::ReverseCalculator => self.body_reverse_calculator(),
             Mode::OptimalCalculator => self.body_optimal_calculator(),
+            Mode::ParetoCalculator => self.body_pareto_calculator(),
         };
         let footer = self.footer();
 
@@ -376,6 +1551,17 @@ impl MixCalculator {
             .text_size(12)
             .padding(2);
 
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let thread_picker = pick_list(
+            (1..=cores).collect::<Vec<_>>(),
+            Some(self.search_pool.current_num_threads()),
+            Message::ChangedThreadCount,
+        )
+        .text_size(12)
+        .padding(2);
+        let low_priority_checkbox = checkbox("Low priority", self.thread_settings.low_priority)
+            .on_toggle(Message::ToggledLowPriority);
+
         let grow_tent_checkbox =
             checkbox("Grow Tent", self.expenses.grow_tent).on_toggle(Message::ToggledGrowTent);
         let pgr_checkbox = checkbox("PGR", self.expenses.additives.contains(Additive::PGR))
@@ -402,6 +1588,13 @@ impl MixCalculator {
         row![
             row![text("Mode").size(15), mode_picker,].spacing(5),
             row![text("Theme").size(15), theme_picker,].spacing(5),
+            row![
+                text("Threads").size(15),
+                thread_picker,
+                low_priority_checkbox
+            ]
+            .spacing(5)
+            .align_y(Alignment::Center),
             horizontal_space(),
             grow_tent_checkbox,
             pgr_checkbox,
@@ -417,13 +1610,39 @@ impl MixCalculator {
     }
 
     fn recipe_button(&self) -> Element<'_, Message> {
-        let on_press = (!self.calculating_recipe).then_some(Message::CalculateRecipe);
+        let can_search = match self.mode {
+            Mode::OptimalCalculator => {
+                (!self.custom_objective_enabled || self.objective_expression.is_ok())
+                    && (!self.start_recipe_enabled || self.start_recipe.is_ok())
+            }
+            Mode::ParetoCalculator => !self.objectives_selected.is_empty(),
+            Mode::ReverseCalculator if self.batch_enabled => {
+                self.batch_targets.as_ref().is_ok_and(|t| !t.is_empty())
+            }
+            _ => true,
+        };
+        let on_press = (!self.calculating_recipe && can_search).then_some(Message::CalculateRecipe);
         let button_text = match self.calculating_recipe {
             false => "Search for recipe",
             true => "Working...",
         };
 
-        button(button_text).on_press_maybe(on_press).into()
+        let cancelling = self.cancellation_token.is_cancelled();
+        let cancel_on_press =
+            (self.calculating_recipe && !cancelling).then_some(Message::CancelCalculation);
+        let cancel_text = match self.calculating_recipe && cancelling {
+            false => "Cancel",
+            true => "Cancelling...",
+        };
+
+        row![
+            button(button_text).on_press_maybe(on_press),
+            button(cancel_text)
+                .on_press_maybe(cancel_on_press)
+                .style(button::danger)
+        ]
+        .spacing(5)
+        .into()
     }
 
     fn recipe_container(&self) -> Element<'_, Message> {
@@ -440,6 +1659,61 @@ impl MixCalculator {
         .into()
     }
 
+    fn ranked_recipes_list(&self) -> Element<'_, Message> {
+        let entries = self
+            .ranked_recipes
+            .iter()
+            .enumerate()
+            .map(|(index, (r, score))| {
+                let mut header = format!("#{}  |  Score: {:.2}", index + 1, *score as f32 / 100.0);
+                if let Some(edit) = self.ranked_recipe_edits.get(index) {
+                    header.push_str(&format!("  |  {edit}"));
+                }
+                let steps = r
+                    .intermediates()
+                    .iter()
+                    .map(|i| i.name())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                let mut effects: Vec<_> = r.calculate_effects().iter().collect();
+                effects.sort_by_key(|e| e.to_string());
+                let details = effects
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let button_text = column([
+                    text(header).into(),
+                    text(format!("{} + {steps}", r.base())).size(12).into(),
+                    text(details).size(10).into(),
+                ]);
+                let style = match self.ranked_recipe_selected == Some(index) {
+                    true => button::primary,
+                    false => button::secondary,
+                };
+                let message =
+                    (!self.calculating_recipe).then_some(Message::SelectedRankedRecipe(index));
+                button(button_text)
+                    .on_press_maybe(message)
+                    .style(style)
+                    .width(Length::Fill)
+                    .into()
+            })
+            .collect::<Vec<_>>();
+
+        let list: Element<'_, Message> = match entries.is_empty() {
+            true => text(self.no_results_text()).into(),
+            false => scrollable(column(entries).padding(5).spacing(5)).into(),
+        };
+
+        container(list)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(container::rounded_box)
+            .padding(5)
+            .into()
+    }
+
     fn recipe_info(&self) -> Element<'_, Message> {
         let r = self.active_recipe.clone().unwrap_or_default();
 
@@ -449,14 +1723,18 @@ impl MixCalculator {
         let profit_margin =
             text(format!("{:.1}%", 100.0 * r.profit_margin(self.expenses))).size(15);
 
-        row![
-            horizontal_space(),
-            column![text("Production Cost"), production_cost].align_x(Alignment::Center),
-            column![text("Sell Price"), sell_price].align_x(Alignment::Center),
-            column![text("Profit Margin"), profit_margin].align_x(Alignment::Center),
-            horizontal_space(),
+        column![
+            row![
+                horizontal_space(),
+                column![text("Production Cost"), production_cost].align_x(Alignment::Center),
+                column![text("Sell Price"), sell_price].align_x(Alignment::Center),
+                column![text("Profit Margin"), profit_margin].align_x(Alignment::Center),
+                horizontal_space(),
+            ]
+            .spacing(20),
+            self.search_stats_panel()
         ]
-        .spacing(20)
+        .spacing(5)
         .into()
     }
 
@@ -472,6 +1750,84 @@ impl MixCalculator {
             .into()
     }
 
+    fn ingredients_panel(&self) -> Element<'_, Message> {
+        let preset = IngredientPreset::ALL
+            .iter()
+            .find(|p| p.ingredients() == self.allowed_ingredients)
+            .copied();
+        let preset_picker = pick_list(
+            IngredientPreset::ALL,
+            preset,
+            Message::ChangedIngredientPreset,
+        )
+        .placeholder("Custom")
+        .text_size(12);
+
+        let ingredient_checkboxes = column(
+            Intermediate::ALL
+                .iter()
+                .map(|i| {
+                    checkbox(i.name(), self.allowed_ingredients.contains(*i))
+                        .on_toggle(|b| Message::ToggledIngredient(*i, b))
+                        .text_size(12)
+                        .into()
+                })
+                .collect::<Vec<_>>(),
+        )
+        .spacing(5);
+
+        column![text("Ingredients"), preset_picker, ingredient_checkboxes]
+            .spacing(5)
+            .align_x(Alignment::Center)
+            .into()
+    }
+
+    fn starting_effects_panel(&self) -> Element<'_, Message> {
+        let starting_effects_checkbox =
+            checkbox("Start from a mixed product", self.starting_effects_enabled)
+                .on_toggle(Message::ToggledStartingEffects)
+                .text_size(12);
+        if !self.starting_effects_enabled {
+            return starting_effects_checkbox.into();
+        }
+
+        let full = self.starting_effects.len() >= MAX_EFFECTS;
+        let effect_checkboxes = column(
+            Effect::ALL
+                .iter()
+                .map(|e| {
+                    let e = *e;
+                    let selected = self.starting_effects.contains(e);
+                    checkbox(e.to_string(), selected)
+                        .on_toggle_maybe(
+                            (selected || !full)
+                                .then_some(move |b| Message::ToggledStartingEffect(e, b)),
+                        )
+                        .text_size(12)
+                        .into()
+                })
+                .collect::<Vec<_>>(),
+        )
+        .spacing(5);
+
+        column![
+            starting_effects_checkbox,
+            text(format!(
+                "Starting Effects ({}/{MAX_EFFECTS})",
+                self.starting_effects.len()
+            ))
+            .size(12),
+            container(scrollable(effect_checkboxes).width(Length::Fill))
+                .height(150)
+                .width(200)
+                .style(container::rounded_box)
+                .padding(5)
+        ]
+        .spacing(5)
+        .align_x(Alignment::Center)
+        .into()
+    }
+
     fn body_reverse_calculator(&self) -> Element<'_, Message> {
         // Sort picker
         let sort_picker = pick_list(
@@ -484,7 +1840,10 @@ impl MixCalculator {
         let mut available_effects: Vec<Effect> = Effect::ALL
             .iter()
             .copied()
-            .filter(|e| !self.target_effects.contains(*e))
+            .filter(|e| {
+                !self.effect_constraints.required.contains(*e)
+                    && !self.effect_constraints.forbidden.contains(*e)
+            })
             .collect();
         available_effects.sort_by(
             self.sort_type_selected
@@ -504,12 +1863,21 @@ impl MixCalculator {
                         );
                         let button_text =
                             column([text(title).into(), text(details).size(10).into()]);
-                        let message =
-                            (self.target_effects.len() < 8).then_some(Message::AddedEffect(*e));
-                        button(button_text)
-                            .on_press_maybe(message)
-                            .width(Length::FillPortion(1))
-                            .into()
+                        let message = (self.effect_constraints.required.len() < 8)
+                            .then_some(Message::AddedEffect(*e));
+                        let forbid_message =
+                            (!self.effect_constraints.exact).then_some(Message::ForbadeEffect(*e));
+                        row![
+                            button(button_text)
+                                .on_press_maybe(message)
+                                .width(Length::FillPortion(1)),
+                            button(text("Forbid").size(10))
+                                .on_press_maybe(forbid_message)
+                                .style(button::danger)
+                        ]
+                        .align_y(Alignment::Center)
+                        .spacing(5)
+                        .into()
                     })
                     .collect::<Vec<_>>(),
             )
@@ -520,43 +1888,58 @@ impl MixCalculator {
         .width(Length::FillPortion(1));
 
         // Target effects section
-        // List of selected effects
-        let mut target_effects: Vec<Effect> = Effect::ALL
-            .iter()
-            .copied()
-            .filter(|e| self.target_effects.contains(*e))
-            .collect();
-        target_effects.sort_by(
-            self.sort_type_selected
-                .unwrap_or(SortType::AlphabeticalAscending)
-                .sort_function(),
-        );
-        let target_effects_list = container(scrollable(
-            column(
-                target_effects
-                    .iter()
-                    .map(|e| {
-                        let header = e.to_string();
-                        let details = format!(
-                            "Addictiveness: {:.2} | Price Modifier: {:.2}",
-                            e.addictiveness(),
-                            e.price_modifier()
-                        );
-                        let button_text =
-                            column([text(header).into(), text(details).size(10).into()]);
-                        button(button_text)
-                            .on_press(Message::RemovedEffect(*e))
-                            .width(Length::FillPortion(1))
-                            .into()
-                    })
-                    .collect::<Vec<_>>(),
-            )
-            .padding(5)
-            .spacing(5),
-        ))
-        .style(container::rounded_box)
-        .width(Length::FillPortion(1))
-        .height(Length::FillPortion(1));
+        // Lists of required and forbidden effects
+        let constrained_effects_list = |constraint: EffectConstraint| {
+            let mut effects: Vec<Effect> = Effect::ALL
+                .iter()
+                .copied()
+                .filter(|e| match constraint {
+                    EffectConstraint::Required => self.effect_constraints.required.contains(*e),
+                    _ => self.effect_constraints.forbidden.contains(*e),
+                })
+                .collect();
+            effects.sort_by(
+                self.sort_type_selected
+                    .unwrap_or(SortType::AlphabeticalAscending)
+                    .sort_function(),
+            );
+            let style = match constraint {
+                EffectConstraint::Required => button::primary,
+                _ => button::danger,
+            };
+            container(scrollable(
+                column(
+                    effects
+                        .iter()
+                        .map(|e| {
+                            let header = e.to_string();
+                            let details = format!(
+                                "Addictiveness: {:.2} | Price Modifier: {:.2}",
+                                e.addictiveness(),
+                                e.price_modifier()
+                            );
+                            let button_text =
+                                column([text(header).into(), text(details).size(10).into()]);
+                            button(button_text)
+                                .on_press(Message::RemovedEffect(*e))
+                                .style(style)
+                                .width(Length::FillPortion(1))
+                                .into()
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .padding(5)
+                .spacing(5),
+            ))
+            .style(container::rounded_box)
+            .width(Length::FillPortion(1))
+        };
+        let target_effects_list =
+            constrained_effects_list(EffectConstraint::Required).height(Length::FillPortion(2));
+        let forbidden_effects_list =
+            constrained_effects_list(EffectConstraint::Forbidden).height(Length::FillPortion(1));
+        let exact_checkbox = checkbox("Exactly these effects", self.effect_constraints.exact)
+            .on_toggle(Message::ToggledExactEffects);
 
         // Info about selected effects
         let price_modifier = text(format!(
@@ -584,15 +1967,88 @@ impl MixCalculator {
         ]
         .padding(10);
 
-        let target_effects_section = column![target_effects_list, effects_info_box];
+        let target_effects_section = column![
+            text("Required"),
+            target_effects_list,
+            exact_checkbox,
+            text("Forbidden"),
+            forbidden_effects_list,
+            effects_info_box
+        ]
+        .spacing(5);
 
         let recipe_section = self.recipe_column();
 
+        let goal_picker = pick_list(
+            ReverseGoal::ALL,
+            Some(self.reverse_goal_selected),
+            Message::ChangedReverseGoal,
+        );
+        let depth_picker: Element<'_, Message> =
+            match self.reverse_algorithm_selected == ReverseAlgorithm::MeetInTheMiddle {
+                true => row![
+                    text("up to"),
+                    pick_list(
+                        [8, 10, 12, 14, 16],
+                        Some(self.reverse_depth_selected),
+                        Message::ChangedReverseDepth,
+                    ),
+                    text("ingredients")
+                ]
+                .align_y(Alignment::Center)
+                .spacing(5)
+                .into(),
+                false => row![].into(),
+            };
+        let algorithm_picker: Element<'_, Message> =
+            match self.reverse_goal_selected == ReverseGoal::Shortest && !self.batch_enabled {
+                true => row![
+                    text("with"),
+                    pick_list(
+                        ReverseAlgorithm::ALL,
+                        Some(self.reverse_algorithm_selected),
+                        Message::ChangedReverseAlgorithm,
+                    ),
+                    depth_picker
+                ]
+                .align_y(Alignment::Center)
+                .spacing(5)
+                .into(),
+                false => row![].into(),
+            };
+        let batch_checkbox = checkbox("Search a list of targets", self.batch_enabled)
+            .on_toggle_maybe((!self.calculating_recipe).then_some(Message::ToggledBatch));
+
+        let targets_section: Element<'_, Message> = match self.batch_enabled {
+            true => row![self.batch_targets_panel(), self.batch_table()]
+                .spacing(20)
+                .into(),
+            false => row![available_effects_list, target_effects_section]
+                .spacing(20)
+                .into(),
+        };
+
         column![
-            sort_picker,
             row![
-                available_effects_list,
-                target_effects_section,
+                sort_picker,
+                batch_checkbox,
+                horizontal_space(),
+                text("Find the"),
+                goal_picker,
+                text("recipe"),
+                algorithm_picker
+            ]
+            .align_y(Alignment::Center)
+            .spacing(5),
+            row![
+                targets_section,
+                container(scrollable(
+                    column![self.starting_effects_panel(), self.ingredients_panel()]
+                        .spacing(10)
+                        .align_x(Alignment::Center)
+                ))
+                .style(container::rounded_box)
+                .padding(5),
                 recipe_section
             ]
             .spacing(20)
@@ -602,9 +2058,119 @@ impl MixCalculator {
         .into()
     }
 
+    fn batch_targets_panel(&self) -> Element<'_, Message> {
+        let editor = text_editor(&self.batch_targets_text)
+            .placeholder("Energizing, Gingeritis\nCalming, Sneaky, Thought-Provoking")
+            .on_action(Message::ChangedBatchTargets)
+            .size(12)
+            .height(Length::Fill);
+
+        let feedback = match &self.batch_targets {
+            Ok(targets) => text(format!(
+                "{} targets. Paste one target per line, with its effects separated by commas.",
+                targets.len()
+            ))
+            .size(10),
+            Err(error) => text(error.clone()).size(10).style(text::danger),
+        };
+
+        column![text("Targets"), editor, feedback]
+            .spacing(5)
+            .width(Length::FillPortion(1))
+            .into()
+    }
+
+    /// Lists the recipes of the last batch search, with one row per base under every target.
+    fn batch_table(&self) -> Element<'_, Message> {
+        let cell =
+            |content: String, portion| text(content).size(12).width(Length::FillPortion(portion));
+        let header = row![
+            cell(String::from("Base"), 2),
+            cell(String::from("Recipe"), 5),
+            cell(String::from("Production Cost"), 2),
+            cell(String::from("Steps"), 1),
+        ]
+        .padding(Padding::from([0, 15]));
+
+        let targets = self
+            .batch_results
+            .iter()
+            .enumerate()
+            .map(|(target, (effects, recipes))| {
+                let mut names: Vec<_> = effects.iter().map(|e| e.to_string()).collect();
+                names.sort();
+                let best = self.best_batch_recipe(target);
+                let rows = (0..recipes.len()).map(|start| {
+                    let base = Base::ALL[start];
+                    let base = match best == Some(start) {
+                        true => format!("{base} (best)"),
+                        false => base.to_string(),
+                    };
+                    let row_text = match self.batch_recipe(target, start) {
+                        Some(r) => row![
+                            cell(base, 2),
+                            cell(
+                                r.intermediates()
+                                    .iter()
+                                    .map(|i| i.name())
+                                    .collect::<Vec<_>>()
+                                    .join(" -> "),
+                                5
+                            ),
+                            cell(
+                                format!("~${:.0}", r.production_cost(self.expenses).abs()),
+                                2
+                            ),
+                            cell(r.intermediates().len().to_string(), 1),
+                        ],
+                        None => row![cell(base, 2), cell(String::from("Not found"), 8)],
+                    };
+                    let style = match self.batch_recipe_selected == Some((target, start)) {
+                        true => button::primary,
+                        false => button::secondary,
+                    };
+                    let message = (!self.calculating_recipe)
+                        .then_some(Message::SelectedBatchRecipe(target, start));
+                    button(row_text)
+                        .on_press_maybe(message)
+                        .style(style)
+                        .width(Length::Fill)
+                        .into()
+                });
+
+                column![text(names.join(", ")).size(15)]
+                    .extend(rows)
+                    .spacing(5)
+                    .into()
+            })
+            .collect::<Vec<Element<'_, Message>>>();
+
+        let table: Element<'_, Message> = match targets.is_empty() {
+            true => text("No targets searched yet!").into(),
+            false => column![header, scrollable(column(targets).padding(5).spacing(15))]
+                .spacing(5)
+                .into(),
+        };
+
+        container(table)
+            .width(Length::FillPortion(2))
+            .height(Length::Fill)
+            .style(container::rounded_box)
+            .padding(5)
+            .into()
+    }
+
     fn body_optimal_calculator(&self) -> Element<'_, Message> {
-        let base_picker =
-            pick_list(Base::ALL, Some(self.base_selected), Message::ChangedBase).text_size(12);
+        let optimal_base = match self.any_base_selected {
+            true => OptimalBase::Any,
+            false => OptimalBase::Single(self.base_selected),
+        };
+        let base_picker = pick_list(
+            OptimalBase::ALL,
+            Some(optimal_base),
+            Message::ChangedOptimalBase,
+        )
+        .text_size(12);
         let metric_picker = pick_list(
             Metric::ALL,
             Some(self.metric_selected),
@@ -612,24 +2178,452 @@ impl MixCalculator {
         )
         .text_size(12);
 
-        let depth_range: Vec<_> = (0..=8).collect();
+        let weights = [0.0, 0.5, 1.0, 2.0, 5.0, 10.0, 100.0];
+        let metric_weight_picker = pick_list(
+            weights,
+            Some(self.metric_weight_selected),
+            Message::ChangedMetricWeight,
+        )
+        .text_size(12);
+        let addictiveness_weight_picker = pick_list(
+            weights,
+            Some(self.addictiveness_weight_selected),
+            Message::ChangedAddictivenessWeight,
+        )
+        .text_size(12);
+
+        let depth_range: Vec<_> = (0..=self.max_depth()).collect();
         let depth_slider = pick_list(
             depth_range,
             Some(self.depth_selected),
             Message::ChangedDepth,
         );
 
+        let algorithm_picker = pick_list(
+            OptimalAlgorithm::ALL,
+            Some(self.optimal_algorithm_selected),
+            Message::ChangedOptimalAlgorithm,
+        )
+        .text_size(12);
+
+        let algorithm_options = match self.optimal_algorithm_selected {
+            OptimalAlgorithm::StateSearch => {
+                let result_count_picker = pick_list(
+                    [1, 5, 10, 25, 50],
+                    Some(self.result_count_selected),
+                    Message::ChangedResultCount,
+                );
+                column![text("Results"), result_count_picker]
+            }
+            OptimalAlgorithm::BranchAndBound => column![
+                text("Finds the single best recipe for the score, skipping branches that can't beat it.")
+                    .size(10)
+            ],
+            OptimalAlgorithm::BeamSearch => {
+                let beam_width_picker = pick_list(
+                    [10, 100, 1000, 10000],
+                    Some(self.beam_width_selected),
+                    Message::ChangedBeamWidth,
+                );
+                column![
+                    text("Beam Width"),
+                    beam_width_picker,
+                    text("Only keeps the best recipes of every depth, so it may miss the best recipe.")
+                        .size(10)
+                ]
+            }
+            OptimalAlgorithm::SimulatedAnnealing => {
+                let iterations_picker = pick_list(
+                    [10_000, 100_000, 1_000_000],
+                    Some(self.annealing_iterations_selected),
+                    Message::ChangedAnnealingIterations,
+                );
+                let deterministic_checkbox =
+                    checkbox("Same result every time", self.deterministic_enabled)
+                        .on_toggle(Message::ToggledDeterministic)
+                        .text_size(12);
+                column![
+                    text("Iterations"),
+                    iterations_picker,
+                    deterministic_checkbox,
+                    text("Randomly tweaks recipes, so it may miss the best recipe.").size(10)
+                ]
+            }
+            OptimalAlgorithm::TimeBudget => {
+                let budget_picker = pick_list(
+                    [1, 5, 10, 30, 60],
+                    Some(self.time_budget_selected),
+                    Message::ChangedTimeBudget,
+                );
+                column![
+                    text("Time Budget (seconds)"),
+                    budget_picker,
+                    text("Searches deeper and deeper until the time runs out, showing better recipes as they are found.")
+                        .size(10)
+                ]
+            }
+        };
+
+        let custom_objective_checkbox = checkbox("Custom objective", self.custom_objective_enabled)
+            .on_toggle(Message::ToggledCustomObjective)
+            .text_size(12);
+
+        let objective_options = match self.custom_objective_enabled {
+            false => column![
+                column![text("Metric"), metric_picker]
+                    .spacing(5)
+                    .align_x(Alignment::Center),
+                column![
+                    text("Weights"),
+                    row![text("Metric").size(12), metric_weight_picker]
+                        .spacing(5)
+                        .align_y(Alignment::Center),
+                    row![text("Addictiveness").size(12), addictiveness_weight_picker]
+                        .spacing(5)
+                        .align_y(Alignment::Center),
+                    text("Score = weighted metric + weighted addictiveness").size(10)
+                ]
+                .spacing(5)
+                .align_x(Alignment::Center),
+            ],
+            true => column![self.objective_expression_editor()],
+        };
+
+        let single_edit =
+            self.start_recipe_enabled && self.improve_mode_selected == ImproveMode::SingleEdit;
+        let algorithm_section = match (single_edit, self.custom_objective_enabled) {
+            (true, _) => {
+                let result_count_picker = pick_list(
+                    [1, 5, 10, 25, 50],
+                    Some(self.result_count_selected),
+                    Message::ChangedResultCount,
+                );
+                column![text("Results"), result_count_picker]
+            }
+            (false, false) => column![
+                column![text("Algorithm"), algorithm_picker]
+                    .spacing(5)
+                    .align_x(Alignment::Center),
+                algorithm_options.spacing(5).align_x(Alignment::Center),
+            ],
+            (false, true) => column![
+                text(
+                    "Custom objectives are searched with a brute-force DFS, so keep the depth low."
+                )
+                .size(10)
+            ],
+        };
+
+        let depth_label = match self.start_recipe_enabled {
+            true => "Ingredients to Add",
+            false => "Depth",
+        };
+        let depth_section: Element<'_, Message> = match single_edit
+            || (!self.custom_objective_enabled
+                && self.optimal_algorithm_selected == OptimalAlgorithm::TimeBudget)
+        {
+            true => column![].into(),
+            false => column![text(depth_label), depth_slider]
+                .spacing(5)
+                .align_x(Alignment::Center)
+                .into(),
+        };
+
+        let start_recipe_checkbox =
+            checkbox("Start from an existing recipe", self.start_recipe_enabled)
+                .on_toggle(Message::ToggledStartRecipe)
+                .text_size(12);
+        let start_section: Element<'_, Message> = match self.start_recipe_enabled {
+            false => column![
+                column![text("Base"), base_picker]
+                    .spacing(5)
+                    .align_x(Alignment::Center),
+                self.starting_effects_panel()
+            ]
+            .spacing(10)
+            .align_x(Alignment::Center)
+            .into(),
+            true => self.start_recipe_editor(),
+        };
+
+        let options_panel = container(scrollable(
+            column![
+                start_recipe_checkbox,
+                start_section,
+                custom_objective_checkbox,
+                objective_options.spacing(10).align_x(Alignment::Center),
+                depth_section,
+                algorithm_section.spacing(10).align_x(Alignment::Center),
+                self.ingredients_panel()
+            ]
+            .spacing(10)
+            .align_x(Alignment::Center),
+        ))
+        .height(Length::Fill)
+        .padding(20);
+
+        row![
+            column![options_panel, self.recipe_button()].align_x(Alignment::Center),
+            column![
+                match self.searches_every_base() {
+                    true => self.base_comparison_table(),
+                    false => self.ranked_recipes_list(),
+                },
+                self.recipe_info()
+            ]
+            .spacing(5)
+        ]
+        .spacing(20)
+        .padding(Padding::from([5, 10]))
+        .into()
+    }
+
+    fn start_recipe_editor(&self) -> Element<'_, Message> {
+        let input = text_input("OG Kush + Cuke -> Banana", &self.start_recipe_text)
+            .on_input(Message::ChangedStartRecipeText)
+            .size(12)
+            .width(250);
+        let load_button = button(text("Use the current recipe").size(12))
+            .on_press_maybe((!self.calculating_recipe).then_some(Message::UsedActiveRecipeAsStart));
+
+        let feedback = match &self.start_recipe {
+            Ok(r) => text(format!(
+                "{} with {} steps",
+                r.start(),
+                r.intermediates().len()
+            ))
+            .size(10),
+            Err(error) => text(error.to_string()).size(10).style(text::danger),
+        };
+
+        let improve_mode_picker = pick_list(
+            ImproveMode::ALL,
+            Some(self.improve_mode_selected),
+            Message::ChangedImproveMode,
+        )
+        .text_size(12);
+
+        column![
+            text("Starting Recipe"),
+            input,
+            feedback,
+            load_button,
+            improve_mode_picker
+        ]
+        .spacing(5)
+        .width(250)
+        .align_x(Alignment::Center)
+        .into()
+    }
+
+    fn objective_expression_editor(&self) -> Element<'_, Message> {
+        let input = text_input(
+            DEFAULT_OBJECTIVE_EXPRESSION,
+            &self.objective_expression_text,
+        )
+        .on_input(Message::ChangedObjectiveExpression)
+        .size(12)
+        .width(250);
+
+        let feedback = match &self.objective_expression {
+            Ok(_) => text(
+                "Variables: profit, profit_margin, sell_price, production_cost, addictiveness, steps, and every effect name (1 if present, 0 otherwise). Append `where` and a condition like `!Toxic` to skip recipes.",
+            )
+            .size(10),
+            Err(error) => text(error.to_string()).size(10).style(text::danger),
+        };
+
+        column![text("Objective"), input, feedback]
+            .spacing(5)
+            .width(250)
+            .align_x(Alignment::Center)
+            .into()
+    }
+
+    fn base_comparison_table(&self) -> Element<'_, Message> {
+        let cell = |content: String| text(content).size(12).width(Length::FillPortion(1));
+        let header = row![
+            cell(String::from("Base")),
+            cell(String::from("Score")),
+            cell(String::from("Sell Price")),
+            cell(String::from("Production Cost")),
+            cell(String::from("Profit Margin")),
+            cell(String::from("Steps")),
+        ]
+        .padding(Padding::from([0, 15]));
+
+        let winner_score = self.base_comparison.iter().map(|(_, score)| *score).max();
+        let rows = self
+            .base_comparison
+            .iter()
+            .enumerate()
+            .map(|(index, (r, score))| {
+                let base = match Some(*score) == winner_score {
+                    true => format!("{} (best)", r.base()),
+                    false => r.base().to_string(),
+                };
+                let row_text = row![
+                    cell(base),
+                    cell(format!("{:.2}", *score as f32 / 100.0)),
+                    cell(format!("${:.0}", r.sell_price())),
+                    cell(format!("~${:.0}", r.production_cost(self.expenses).abs())),
+                    cell(format!("{:.1}%", 100.0 * r.profit_margin(self.expenses))),
+                    cell(r.intermediates().len().to_string()),
+                ];
+                let style = match self.base_comparison_selected == Some(index) {
+                    true => button::primary,
+                    false => button::secondary,
+                };
+                let message =
+                    (!self.calculating_recipe).then_some(Message::SelectedBaseComparison(index));
+                button(row_text)
+                    .on_press_maybe(message)
+                    .style(style)
+                    .width(Length::Fill)
+                    .into()
+            })
+            .collect::<Vec<_>>();
+
+        let table: Element<'_, Message> = match rows.is_empty() {
+            true => text(self.no_results_text()).into(),
+            false => column![header, scrollable(column(rows).padding(5).spacing(5))]
+                .spacing(5)
+                .into(),
+        };
+
+        container(table)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(container::rounded_box)
+            .padding(5)
+            .into()
+    }
+
+    /// Shows the statistics of the last search, which can be collapsed.
+    fn search_stats_panel(&self) -> Element<'_, Message> {
+        let arrow = match self.search_stats_expanded {
+            true => "v",
+            false => ">",
+        };
+        let header = button(text(format!("{arrow} Search statistics")).size(12))
+            .on_press(Message::ToggledSearchStats)
+            .style(button::text);
+        if !self.search_stats_expanded {
+            return header.into();
+        }
+
+        let details = match self.search_stats {
+            Some(stats) => {
+                let unique_effect_sets = match stats.unique_effect_sets {
+                    Some(count) => count.to_string(),
+                    None => String::from("not tracked"),
+                };
+                column![
+                    text(format!("Nodes expanded: {}", stats.nodes_expanded)),
+                    text(format!("Recipes evaluated: {}", stats.leaves_evaluated)),
+                    text(format!("Unique effect sets: {unique_effect_sets}")),
+                    text(format!("Branches pruned: {}", stats.nodes_pruned)),
+                    text(format!("Wall time: {:.2}s", stats.elapsed.as_secs_f32())),
+                    text(format!("Threads: {}", stats.threads)),
+                ]
+            }
+            None if self.answered_by_database => {
+                column![text("Answered by the precomputed database")]
+            }
+            None => column![text("No search has finished yet")],
+        };
+
+        column![header, details.spacing(2).padding(Padding::from([0, 10]))]
+            .spacing(2)
+            .into()
+    }
+
+    fn pareto_recipes_list(&self) -> Element<'_, Message> {
+        let entries = self
+            .pareto_recipes
+            .iter()
+            .enumerate()
+            .map(|(index, (r, values))| {
+                let header = self
+                    .objectives_selected
+                    .iter()
+                    .zip(values)
+                    .map(|(o, v)| format!("{o}: {}", o.format_value(*v)))
+                    .collect::<Vec<_>>()
+                    .join("  |  ");
+                let steps = r
+                    .intermediates()
+                    .iter()
+                    .map(|i| i.name())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                let button_text = column([
+                    text(header).into(),
+                    text(format!("{} + {steps}", r.base())).size(12).into(),
+                ]);
+                let style = match self.pareto_recipe_selected == Some(index) {
+                    true => button::primary,
+                    false => button::secondary,
+                };
+                let message =
+                    (!self.calculating_recipe).then_some(Message::SelectedParetoRecipe(index));
+                button(button_text)
+                    .on_press_maybe(message)
+                    .style(style)
+                    .width(Length::Fill)
+                    .into()
+            })
+            .collect::<Vec<_>>();
+
+        let list: Element<'_, Message> = match entries.is_empty() {
+            true => text("No recipes found!").into(),
+            false => scrollable(column(entries).padding(5).spacing(5)).into(),
+        };
+
+        container(list)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(container::rounded_box)
+            .padding(5)
+            .into()
+    }
+
+    fn body_pareto_calculator(&self) -> Element<'_, Message> {
+        let base_picker =
+            pick_list(Base::ALL, Some(self.base_selected), Message::ChangedBase).text_size(12);
+
+        let depth_range: Vec<_> = (0..=self.max_depth()).collect();
+        let depth_slider = pick_list(
+            depth_range,
+            Some(self.depth_selected),
+            Message::ChangedDepth,
+        );
+
+        let objective_checkboxes = column(
+            Objective::ALL
+                .iter()
+                .map(|o| {
+                    checkbox(o.to_string(), self.objectives_selected.contains(o))
+                        .on_toggle(|b| Message::ToggledObjective(*o, b))
+                        .into()
+                })
+                .collect::<Vec<_>>(),
+        )
+        .spacing(5);
+
         let options_panel = container(scrollable(
             column![
                 column![text("Base"), base_picker]
                     .spacing(5)
                     .align_x(Alignment::Center),
-                column![text("Metric"), metric_picker]
+                self.starting_effects_panel(),
+                column![text("Depth"), depth_slider]
                     .spacing(5)
                     .align_x(Alignment::Center),
-                column![text("Depth"), depth_slider]
+                column![text("Objectives"), objective_checkboxes]
                     .spacing(5)
-                    .align_x(Alignment::Center)
+                    .align_x(Alignment::Center),
+                self.ingredients_panel()
             ]
             .spacing(10)
             .align_x(Alignment::Center),
@@ -639,7 +2633,7 @@ impl MixCalculator {
 
         row![
             column![options_panel, self.recipe_button()].align_x(Alignment::Center),
-            column![self.recipe_container(), self.recipe_info()]
+            column![self.pareto_recipes_list(), self.recipe_info()]
         ]
         .spacing(20)
         .padding(Padding::from([5, 10]))
@@ -648,36 +2642,241 @@ impl MixCalculator {
 
     fn footer(&self) -> Element<'_, Message> {
         let exponentiation_warning = if self.mode == Mode::ReverseCalculator
-            && self.target_effects.len() > 5
+            && self.effect_constraints.required.len() > 5
         {
             text(
                 "!! Selecting more than 5 effects will create exponentially unreasonable calculation times !!",
             )
-        } else if self.mode == Mode::OptimalCalculator && self.depth_selected > 5 {
-            text(
-                "!! Depths higher than 5 will create exponentially unreasonable calculation times !!",
-            )
+        } else if self.mode == Mode::OptimalCalculator
+            && self.custom_objective_enabled
+            && self.depth_selected > 5
+        {
+            text("!! Custom objectives with depths higher than 5 can take minutes to calculate !!")
+        } else if self.mode != Mode::ReverseCalculator
+            && self.depth_selected > 7
+            && self.max_depth() <= 8
+        {
+            text("!! Depths higher than 7 can take several seconds to calculate !!")
         } else {
             text("")
         };
+        let progress_label = match self.search_progress {
+            Some(p) => {
+                let eta = match p.eta() {
+                    Some(eta) => format!("~{:.0}s left", eta.as_secs_f32().ceil()),
+                    None => String::from("estimating..."),
+                };
+                text(format!(
+                    "{:.0}% | {} recipes | {:.1}s elapsed | {eta}",
+                    100.0 * p.fraction,
+                    p.nodes_visited,
+                    p.elapsed.as_secs_f32()
+                ))
+                .size(12)
+            }
+            None => text(""),
+        };
+        let database_label = match (&self.database, &self.database_error) {
+            (Some(database), _) => text(format!(
+                "Database: {} states up to {} ingredients",
+                database.states(),
+                database.depth()
+            )),
+            (None, Some(e)) => text(format!("Database not loaded: {e}")),
+            (None, None) => text(""),
+        }
+        .size(12);
         let progress_bar = progress_bar(0.0..=1.0, self.progress_state)
             .height(5)
             .width(100);
 
-        row![exponentiation_warning, horizontal_space(), progress_bar]
-            .align_y(Alignment::Center)
-            .spacing(5)
-            .padding(5)
-            .into()
+        row![
+            exponentiation_warning,
+            horizontal_space(),
+            database_label,
+            progress_label,
+            progress_bar
+        ]
+        .align_y(Alignment::Center)
+        .spacing(5)
+        .padding(5)
+        .into()
     }
 }
 
+/// Writes `r` on a single line as its starting state followed by its ingredients, in a format `Recipe::from_str` can read back.
+fn recipe_summary(r: &Recipe) -> String {
+    std::iter::once(r.start().to_string())
+        .chain(r.intermediates().iter().map(|i| i.name()))
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// Reads a list of target effect sets, one per line with effects separated by commas, skipping empty lines.
+fn parse_targets(source: &str) -> Result<Vec<EnumSet<Effect>>, String> {
+    source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let effects = line
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(|name| {
+                    Effect::from_name(name)
+                        .ok_or_else(|| format!("line {}: unknown effect `{name}`", index + 1))
+                })
+                .collect::<Result<EnumSet<Effect>, _>>()?;
+            match effects.len() > MAX_EFFECTS {
+                true => Err(format!(
+                    "line {}: a product can't have more than {MAX_EFFECTS} effects",
+                    index + 1
+                )),
+                false => Ok(effects),
+            }
+        })
+        .collect()
+}
+
+/// Hands a channel for search progress to the app and forwards everything sent through it as `Message::ProgressUpdated`.
+fn progress_worker() -> impl Stream<Item = Message> {
+    iced::stream::channel(100, |mut output| async move {
+        let (sender, mut receiver) = mpsc::unbounded();
+        let _ = output.send(Message::ProgressChannelReady(sender)).await;
+
+        while let Some(progress) = receiver.next().await {
+            let _ = output.send(Message::ProgressUpdated(progress)).await;
+        }
+    })
+}
+
+/// Hands a channel for better recipes to the app and forwards everything sent through it as `Message::FoundBetterRecipe`.
+fn improvement_worker() -> impl Stream<Item = Message> {
+    iced::stream::channel(100, |mut output| async move {
+        let (sender, mut receiver) = mpsc::unbounded();
+        let _ = output.send(Message::ImprovementChannelReady(sender)).await;
+
+        while let Some(recipe) = receiver.next().await {
+            let _ = output.send(Message::FoundBetterRecipe(recipe)).await;
+        }
+    })
+}
+
+/// Wraps the constructor of the message carrying the result of a search, so that the statistics returned alongside it are recorded first.
+fn with_stats<T, S>(message: impl Fn(T) -> Message) -> impl Fn((T, S)) -> Message
+where
+    S: Into<Option<SearchStats>>,
+{
+    move |(result, stats)| Message::SearchFinished(stats.into(), Box::new(message(result)))
+}
+
+/// Returns where the database is saved to and loaded from when no path is given, which is next to the executable rather than in the working directory.
+fn default_database_path() -> PathBuf {
+    let exe = std::env::current_exe().ok();
+    let dir = exe
+        .as_deref()
+        .and_then(Path::parent)
+        .unwrap_or(Path::new(""));
+    dir.join(DEFAULT_DATABASE_PATH)
+}
+
+/// Loads the precomputed database from `path`, where a missing file isn't an error.
+fn load_database(path: &Path) -> Result<Option<Arc<ReachableDatabase>>, String> {
+    match ReachableDatabase::load(path) {
+        Ok(database) => Ok(Some(Arc::new(database))),
+        Err(DatabaseError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Removes `--threads N` and `--low-priority` from `args` and returns the settings they describe.
+fn take_thread_settings(args: &mut Vec<String>) -> Result<ThreadSettings, String> {
+    let mut settings = ThreadSettings::default();
+    if let Some(i) = args.iter().position(|a| a == "--low-priority") {
+        args.remove(i);
+        settings.low_priority = true;
+    }
+    if let Some(i) = args.iter().position(|a| a == "--threads") {
+        args.remove(i);
+        if i >= args.len() {
+            return Err(String::from("`--threads` needs a number"));
+        }
+        let threads = args.remove(i);
+        settings.threads = threads
+            .parse()
+            .map_err(|_| format!("invalid thread count `{threads}`"))?;
+    }
+    Ok(settings)
+}
+
+/// Builds the database of reachable states and saves it, for `build-database [depth] [path]`.
+fn build_database(args: &[String]) -> Result<(), String> {
+    let depth = match args.first() {
+        Some(depth) => depth
+            .parse::<u8>()
+            .ok()
+            .filter(|d| *d as usize <= MAX_INGREDIENTS)
+            .ok_or_else(|| format!("invalid depth `{depth}`"))?,
+        None => 8,
+    };
+    let path = args
+        .get(1)
+        .map_or_else(default_database_path, PathBuf::from);
+
+    let progress = ProgressReporter::with_callback(|p| {
+        eprint!(
+            "\r{:.0}% | {:.1}s elapsed",
+            100.0 * p.fraction,
+            p.elapsed.as_secs_f32()
+        );
+    });
+    let database = ReachableDatabase::build(depth as i8, &CancellationToken::new(), &progress)
+        .expect("the build is never cancelled");
+    database
+        .save(&path)
+        .map_err(|e| format!("couldn't write `{}`: {e}", path.display()))?;
+    eprintln!(
+        "\rSaved {} states up to {depth} ingredients to `{}`",
+        database.states(),
+        path.display()
+    );
+
+    Ok(())
+}
+
 fn main() -> iced::Result {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let thread_settings = take_thread_settings(&mut args).unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    });
+    let search_pool = thread_settings.build_pool().unwrap_or_else(|e| {
+        eprintln!("error: couldn't start the worker threads: {e}");
+        std::process::exit(1);
+    });
+    if args.first().is_some_and(|a| a == "build-database") {
+        if let Err(e) = search_pool.install(|| build_database(&args[1..])) {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     iced::application(
         "Duck's Mixing Calculator",
         MixCalculator::update,
         MixCalculator::view,
     )
     .theme(MixCalculator::theme)
-    .run()
+    .subscription(MixCalculator::subscription)
+    .run_with(move || {
+        (
+            MixCalculator::new(thread_settings, search_pool),
+            Task::perform(
+                async { load_database(&default_database_path()) },
+                Message::LoadedDatabase,
+            ),
+        )
+    })
 }
@@ -1,6 +1,12 @@
+pub mod expression;
+mod ingredient_sequence;
+pub mod metric;
 pub mod search_algorithms;
 
+pub use ingredient_sequence::MAX_INGREDIENTS;
+
 use enumset::EnumSet;
+use ingredient_sequence::IngredientSequence;
 
 use crate::{
     effect::{self, Effect},
@@ -8,19 +14,75 @@ use crate::{
     ingredients::{Base, Intermediate},
 };
 
+/// The most effects a product can have at once.
+pub const MAX_EFFECTS: usize = 8;
+
+/// The mixture a `Recipe` starts from: a base along with the effects it already has.
+///
+/// A fresh base only has its own effect, but an already mixed product can be put back into the mixing station and product bought from others can have any effects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StartingState {
+    pub base: Base,
+    pub effects: EnumSet<Effect>,
+}
+
+impl From<Base> for StartingState {
+    /// Creates the `StartingState` of a fresh `base`, which only has the base's own effect.
+    fn from(base: Base) -> Self {
+        Self {
+            base,
+            effects: base.effect().into_iter().collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for StartingState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.is_fresh() {
+            true => write!(f, "{}", self.base),
+            false => write!(
+                f,
+                "{} [{}]",
+                self.base,
+                sorted_names(self.effects).join(", ")
+            ),
+        }
+    }
+}
+
+impl StartingState {
+    /// Returns the `StartingState` of every fresh `Base`, in the order of `Base::ALL`.
+    pub fn every_base() -> Vec<Self> {
+        Base::ALL.iter().map(|b| Self::from(*b)).collect()
+    }
+
+    /// Returns whether this is a fresh base with only its own effect.
+    pub fn is_fresh(&self) -> bool {
+        *self == Self::from(self.base)
+    }
+}
+
+/// Returns the names of `effects` in alphabetical order.
+fn sorted_names(effects: EnumSet<Effect>) -> Vec<String> {
+    let mut names: Vec<String> = effects.iter().map(|e| e.to_string()).collect();
+    names.sort();
+    names
+}
+
 /// A recipe for a drug.
+///
+/// A `Recipe` never allocates and keeps its effects up to date as ingredients are added, so that searches can clone and extend it cheaply.
 #[derive(Clone, Debug)]
 pub struct Recipe {
-    base: Base,
-    intermediates: Vec<Intermediate>,
+    start: StartingState,
+    intermediates: IngredientSequence,
+    /// The effects of the whole recipe, which are always kept in sync with `start` and `intermediates`.
+    effects: EnumSet<Effect>,
 }
 
 impl Default for Recipe {
     fn default() -> Self {
-        Recipe {
-            base: Base::OGKush,
-            intermediates: Vec::default(),
-        }
+        Recipe::with_base(Base::OGKush)
     }
 }
 
@@ -29,11 +91,19 @@ impl ToString for Recipe {
     fn to_string(&self) -> String {
         let mut s = String::new();
         s.push_str("Base:\n");
-        s.push_str(format!("  {}\n\n", self.base).as_str());
+        s.push_str(format!("  {}\n\n", self.start.base).as_str());
+
+        if !self.start.is_fresh() {
+            s.push_str("Starting Effects:\n");
+            sorted_names(self.start.effects)
+                .iter()
+                .for_each(|e| s.push_str(format!("  - {}\n", e).as_str()));
+            s.push('\n');
+        }
 
         let mut i = 1;
         s.push_str("Steps:\n");
-        for ingredient in &self.intermediates {
+        for ingredient in self.intermediates.iter() {
             s.push_str(
                 format!(
                     "  {i}. Add {} to the mixture and stir.\n",
@@ -61,47 +131,88 @@ impl ToString for Recipe {
 impl Recipe {
     /// Creates a `Recipe` with the given `Base`.
     pub fn with_base(base: Base) -> Self {
+        Self::with_start(StartingState::from(base))
+    }
+
+    /// Creates a `Recipe` that starts from the given `StartingState`.
+    pub fn with_start(start: StartingState) -> Self {
         Self {
-            base,
-            intermediates: Vec::new(),
+            start,
+            intermediates: IngredientSequence::default(),
+            effects: start.effects,
         }
     }
 
     /// Adds an ingredient to the end of this `Recipe` and returns the result.  
     /// Helper function to easily build a `Recipe`.
+    ///
+    /// Panics if the `Recipe` already has `MAX_INGREDIENTS` ingredients.
     pub fn add_intermediate(mut self, intermediate: Intermediate) -> Self {
         self.intermediates.push(intermediate);
+        intermediate.apply_to_effect_set(&mut self.effects);
 
         self
     }
 
     /// Returns the base ingredient of this `Recipe`.
     pub fn base(&self) -> Base {
-        self.base
+        self.start.base
+    }
+
+    /// Returns the `StartingState` of this `Recipe`.
+    pub fn start(&self) -> StartingState {
+        self.start
     }
 
-    /// Returns a `Vec` of the `Recipe`'s ingredients.
-    pub fn intermediates(&self) -> &Vec<Intermediate> {
+    /// Returns the `Recipe`'s ingredients in the order they are added.
+    pub fn intermediates(&self) -> &[Intermediate] {
         &self.intermediates
     }
 
-    /// Calculates the effects of this `Recipe`.
-    pub fn calculate_effects(&self) -> EnumSet<Effect> {
-        let mut set = EnumSet::new();
-        if let Some(e) = self.base.effect() {
-            set.insert(e);
-        }
+    /// Compares this `Recipe` to another one that is just as good, where the one searches return is `Less`.
+    ///
+    /// Ties are broken by preferring the recipe with the fewest steps, then the one whose ingredients are the cheapest, then the first one in lexicographic order of base, starting effects and ingredients.
+    /// Bases and ingredients are ordered as in `Base::ALL` and `Intermediate::ALL`.
+    pub fn cmp_tie_break(&self, other: &Self) -> std::cmp::Ordering {
+        let ingredient_cost =
+            |r: &Self| -> f32 { r.intermediates.iter().map(|i| i.purchase_price()).sum() };
+        let lexicographic_key = |r: &Self| (r.start.base as usize, r.start.effects.as_u64());
 
-        for i in &self.intermediates {
-            i.apply_to_effect_set(&mut set);
-        }
+        self.intermediates
+            .len()
+            .cmp(&other.intermediates.len())
+            // Purchase prices are negative, so the cheapest recipe has the highest cost.
+            .then_with(|| ingredient_cost(other).total_cmp(&ingredient_cost(self)))
+            .then_with(|| lexicographic_key(self).cmp(&lexicographic_key(other)))
+            .then_with(|| {
+                let order = |i: &Intermediate| *i as usize;
+                self.intermediates
+                    .iter()
+                    .map(order)
+                    .cmp(other.intermediates.iter().map(order))
+            })
+    }
 
-        set
+    /// Returns the effects of this `Recipe`, which are kept up to date as ingredients are added.
+    pub fn calculate_effects(&self) -> EnumSet<Effect> {
+        self.effects
+    }
+
+    /// Calculates the effects of this `Recipe` from scratch by mixing every ingredient into the `StartingState`.
+    fn effects_from_start(&self) -> EnumSet<Effect> {
+        self.intermediates
+            .iter()
+            .fold(self.start.effects, |mut set, i| {
+                i.apply_to_effect_set(&mut set);
+                set
+            })
     }
 
     /// Calculates the cost to produce one unit of this `Recipe` as a negative f32.
+    ///
+    /// Whatever was spent on the effects of the `StartingState` is not known, so only the base itself is counted.
     pub fn production_cost(&self, expenses: Expenses) -> f32 {
-        self.base.production_cost(expenses)
+        self.start.base.production_cost(expenses)
             + self
                 .intermediates
                 .iter()
@@ -111,7 +222,8 @@ impl Recipe {
 
     /// Calculates the selling price of one unit of this `Recipe`.
     pub fn sell_price(&self) -> f32 {
-        self.base.sell_price() * (1.0 + effect::get_total_price_modifier(self.calculate_effects()))
+        self.start.base.sell_price()
+            * (1.0 + effect::get_total_price_modifier(self.calculate_effects()))
     }
 
     /// Calculates the profit generated by producing and selling one unit of this `Recipe`.
@@ -126,7 +238,7 @@ impl Recipe {
 
     /// Calculates the addictiveness of this `Recipe`, clamped between `f32::MIN_POSITIVE` and `1.0``.
     pub fn addictiveness(&self) -> f32 {
-        (self.base.addictiveness()
+        (self.start.base.addictiveness()
             + self
                 .calculate_effects()
                 .iter()
@@ -135,3 +247,177 @@ impl Recipe {
         .clamp(f32::MIN_POSITIVE, 1.0)
     }
 }
+
+/// An error found while parsing a `Recipe`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRecipeError(pub String);
+
+impl std::fmt::Display for ParseRecipeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseRecipeError {}
+
+impl std::str::FromStr for Recipe {
+    type Err = ParseRecipeError;
+
+    /// Parses a `Recipe` written as its base followed by its ingredients, such as `OG Kush + Cuke -> Banana`, or in the format of `to_string`.
+    ///
+    /// The effects of an already mixed base can be given in brackets after it, such as `OG Kush [Calming, Sneaky] + Cuke`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (names, effects): (Vec<&str>, Vec<&str>) = match s.contains("Steps:") {
+            true => {
+                let mut lines = s.lines().map(str::trim);
+                let base = lines.by_ref().skip_while(|l| *l != "Base:").nth(1);
+                let effects = lines
+                    .clone()
+                    .skip_while(|l| *l != "Starting Effects:")
+                    .skip(1)
+                    .map_while(|l| l.strip_prefix("- "))
+                    .collect();
+                let steps = lines.filter_map(|l| {
+                    l.split_once(". Add ")
+                        .and_then(|(_, step)| step.strip_suffix(" to the mixture and stir."))
+                });
+                (base.into_iter().chain(steps).collect(), effects)
+            }
+            false => {
+                let (before, effects, after) = match s.split_once('[') {
+                    Some((before, rest)) => {
+                        let (effects, after) = rest.split_once(']').ok_or_else(|| {
+                            ParseRecipeError(String::from("the starting effects are missing a `]`"))
+                        })?;
+                        (before, effects.split(',').collect(), after)
+                    }
+                    None => (s, Vec::new(), ""),
+                };
+                let names = [before, after]
+                    .into_iter()
+                    .flat_map(|part| part.split(['+', ',', '\n']))
+                    .flat_map(|part| part.split("->"))
+                    .map(str::trim)
+                    .filter(|part| !part.is_empty())
+                    .collect();
+                (names, effects)
+            }
+        };
+
+        let Some((base, intermediates)) = names.split_first() else {
+            return Err(ParseRecipeError(String::from("the recipe is empty")));
+        };
+        let base = Base::from_name(base)
+            .ok_or_else(|| ParseRecipeError(format!("unknown base `{base}`")))?;
+
+        let mut start = StartingState::from(base);
+        if !effects.is_empty() {
+            start.effects = effects
+                .iter()
+                .map(|name| name.trim())
+                .filter(|name| !name.is_empty())
+                .map(|name| {
+                    Effect::from_name(name)
+                        .ok_or_else(|| ParseRecipeError(format!("unknown effect `{name}`")))
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        if start.effects.len() > MAX_EFFECTS {
+            return Err(ParseRecipeError(format!(
+                "a product can't have more than {MAX_EFFECTS} effects"
+            )));
+        }
+
+        if intermediates.len() > MAX_INGREDIENTS {
+            return Err(ParseRecipeError(format!(
+                "a recipe can't have more than {MAX_INGREDIENTS} ingredients"
+            )));
+        }
+
+        intermediates
+            .iter()
+            .try_fold(Recipe::with_start(start), |r, name| {
+                let intermediate = Intermediate::from_name(name)
+                    .ok_or_else(|| ParseRecipeError(format!("unknown ingredient `{name}`")))?;
+                Ok(r.add_intermediate(intermediate))
+            })
+    }
+}
+
+/// A change of a single ingredient of a `Recipe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecipeEdit {
+    /// Inserts an ingredient before the step at `index`, or at the end if `index` is the number of steps.
+    Insert {
+        index: usize,
+        intermediate: Intermediate,
+    },
+    /// Replaces the ingredient of the step at `index`.
+    Replace {
+        index: usize,
+        intermediate: Intermediate,
+    },
+}
+
+impl std::fmt::Display for RecipeEdit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecipeEdit::Insert {
+                index,
+                intermediate,
+            } => write!(f, "Add {} as step {}", intermediate.name(), index + 1),
+            RecipeEdit::Replace {
+                index,
+                intermediate,
+            } => write!(f, "Replace step {} with {}", index + 1, intermediate.name()),
+        }
+    }
+}
+
+impl RecipeEdit {
+    /// Returns every edit of `r` that inserts or replaces an ingredient with one of `allowed`, skipping replacements that change nothing.
+    ///
+    /// Insertions are skipped if `r` already has `MAX_INGREDIENTS` ingredients.
+    pub fn all(r: &Recipe, allowed: EnumSet<Intermediate>) -> Vec<Self> {
+        let steps = r.intermediates.len();
+        let insertion_points = match r.intermediates.is_full() {
+            true => 0..0,
+            false => 0..steps + 1,
+        };
+        let insertions = insertion_points.flat_map(|index| {
+            allowed.iter().map(move |intermediate| RecipeEdit::Insert {
+                index,
+                intermediate,
+            })
+        });
+        let replacements = (0..steps).flat_map(|index| {
+            allowed
+                .iter()
+                .filter(move |i| *i != r.intermediates[index])
+                .map(move |intermediate| RecipeEdit::Replace {
+                    index,
+                    intermediate,
+                })
+        });
+
+        insertions.chain(replacements).collect()
+    }
+
+    /// Applies this `RecipeEdit` to `r` and returns the result.
+    pub fn apply(&self, r: &Recipe) -> Recipe {
+        let mut edited = r.clone();
+        match *self {
+            RecipeEdit::Insert {
+                index,
+                intermediate,
+            } => edited.intermediates.insert(index, intermediate),
+            RecipeEdit::Replace {
+                index,
+                intermediate,
+            } => edited.intermediates[index] = intermediate,
+        }
+        edited.effects = edited.effects_from_start();
+
+        edited
+    }
+}
@@ -0,0 +1,599 @@
+use super::Recipe;
+use crate::{effect::Effect, expenses::Expenses, ingredients::normalize_name};
+use enumset::EnumSet;
+use std::ops::Range;
+
+/// A value of a `Recipe` that can be used in an `Expression`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    Profit,
+    ProfitMargin,
+    SellPrice,
+    /// The production cost as a positive number, unlike `Recipe::production_cost`.
+    ProductionCost,
+    Addictiveness,
+    Steps,
+    /// `1` if the recipe has the effect, `0` otherwise.
+    Effect(Effect),
+}
+
+impl std::fmt::Display for Variable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Variable::Profit => String::from("profit"),
+            Variable::ProfitMargin => String::from("profit_margin"),
+            Variable::SellPrice => String::from("sell_price"),
+            Variable::ProductionCost => String::from("production_cost"),
+            Variable::Addictiveness => String::from("addictiveness"),
+            Variable::Steps => String::from("steps"),
+            Variable::Effect(effect) => effect.to_string(),
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl Variable {
+    pub const METRICS: &'static [Self] = &[
+        Variable::Profit,
+        Variable::ProfitMargin,
+        Variable::SellPrice,
+        Variable::ProductionCost,
+        Variable::Addictiveness,
+        Variable::Steps,
+    ];
+
+    /// Returns every `Variable`, metrics first.
+    pub fn all() -> impl Iterator<Item = Self> {
+        Self::METRICS
+            .iter()
+            .copied()
+            .chain(Effect::ALL.iter().map(|e| Variable::Effect(*e)))
+    }
+
+    /// Finds the `Variable` with the given name, ignoring case and everything but letters and digits.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = normalize_name(name);
+        Self::all().find(|v| normalize_name(&v.to_string()) == name)
+    }
+
+    fn value(&self, context: &Context) -> f32 {
+        let r = context.recipe;
+        match self {
+            Variable::Profit => r.profit(context.expenses),
+            Variable::ProfitMargin => r.profit_margin(context.expenses),
+            Variable::SellPrice => r.sell_price(),
+            Variable::ProductionCost => -r.production_cost(context.expenses),
+            Variable::Addictiveness => r.addictiveness(),
+            Variable::Steps => r.intermediates().len() as f32,
+            Variable::Effect(effect) => match context.effects.contains(*effect) {
+                true => 1.0,
+                false => 0.0,
+            },
+        }
+    }
+}
+
+/// An error found while parsing an `Expression`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    /// The range of characters of the source the error points at.
+    pub span: Range<usize>,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at column {})", self.message, self.span.start + 1)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+impl BinaryOperator {
+    fn apply(&self, a: f32, b: f32) -> f32 {
+        let truth = |b: bool| match b {
+            true => 1.0,
+            false => 0.0,
+        };
+        match self {
+            BinaryOperator::Add => a + b,
+            BinaryOperator::Subtract => a - b,
+            BinaryOperator::Multiply => a * b,
+            BinaryOperator::Divide => a / b,
+            BinaryOperator::Less => truth(a < b),
+            BinaryOperator::LessOrEqual => truth(a <= b),
+            BinaryOperator::Greater => truth(a > b),
+            BinaryOperator::GreaterOrEqual => truth(a >= b),
+            BinaryOperator::Equal => truth(a == b),
+            BinaryOperator::NotEqual => truth(a != b),
+            BinaryOperator::And => truth(a != 0.0 && b != 0.0),
+            BinaryOperator::Or => truth(a != 0.0 || b != 0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f32),
+    Variable(Variable),
+    Negate(Box<Node>),
+    Not(Box<Node>),
+    Binary(BinaryOperator, Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn evaluate(&self, context: &Context) -> f32 {
+        match self {
+            Node::Number(n) => *n,
+            Node::Variable(v) => v.value(context),
+            Node::Negate(node) => -node.evaluate(context),
+            Node::Not(node) => match node.evaluate(context) {
+                0.0 => 1.0,
+                _ => 0.0,
+            },
+            Node::Binary(operator, a, b) => {
+                operator.apply(a.evaluate(context), b.evaluate(context))
+            }
+        }
+    }
+}
+
+/// Everything an `Expression` needs to evaluate a `Recipe`, computed once per recipe.
+struct Context<'a> {
+    recipe: &'a Recipe,
+    effects: EnumSet<Effect>,
+    expenses: Expenses,
+}
+
+/// A user-written objective to score recipes with, such as `profit * 0.7 + addictiveness * 20 - steps * 2`.
+///
+/// Expressions support numbers, every `Variable`, the arithmetic operators `+ - * /`, the comparisons `< <= > >= == !=`, and the logical operators `! && ||`, where any non-zero value is true.
+/// An expression can end with `where` followed by a condition, in which case recipes that don't satisfy the condition are scored as negative infinity and rejected by `accepts`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    score: Node,
+    condition: Option<Node>,
+}
+
+impl std::str::FromStr for Expression {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Expression {
+    /// Parses an `Expression` from its source.
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            source_len: source.chars().count(),
+            nesting: 0,
+        };
+
+        if parser.peek().is_none() {
+            return Err(ParseError {
+                message: String::from("the objective is empty"),
+                span: 0..0,
+            });
+        }
+
+        let score = parser.parse_or()?;
+        let condition = match parser.peek() {
+            Some(Token::Where) => {
+                parser.position += 1;
+                Some(parser.parse_or()?)
+            }
+            _ => None,
+        };
+
+        if let Some(token) = parser.peek() {
+            let message = match token {
+                Token::Where => String::from("`where` can only be used once"),
+                Token::RightParenthesis => String::from("found `)` without a matching `(`"),
+                token => format!("expected an operator, found {}", token.describe()),
+            };
+            return Err(parser.error_here(message));
+        }
+
+        Ok(Self { score, condition })
+    }
+
+    /// Calculates the value of this `Expression` for the given `Recipe`, where higher is always better.
+    pub fn value(&self, r: &Recipe, expenses: Expenses) -> f32 {
+        let context = Context {
+            recipe: r,
+            effects: r.calculate_effects(),
+            expenses,
+        };
+
+        match &self.condition {
+            Some(condition) if condition.evaluate(&context) == 0.0 => f32::NEG_INFINITY,
+            _ => self.score.evaluate(&context),
+        }
+    }
+
+    /// Returns whether the given `Recipe` satisfies the `where` condition of this `Expression`, which is always the case without one.
+    pub fn accepts(&self, r: &Recipe, expenses: Expenses) -> bool {
+        let context = Context {
+            recipe: r,
+            effects: r.calculate_effects(),
+            expenses,
+        };
+
+        self.condition
+            .as_ref()
+            .is_none_or(|condition| condition.evaluate(&context) != 0.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Identifier(String),
+    Where,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LeftParenthesis,
+    RightParenthesis,
+    Not,
+    And,
+    Or,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Token {
+    /// Describes this `Token` for error messages.
+    fn describe(&self) -> String {
+        let s = match self {
+            Token::Number(n) => return format!("the number `{n}`"),
+            Token::Identifier(name) => return format!("the variable `{name}`"),
+            Token::Where => "where",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::LeftParenthesis => "(",
+            Token::RightParenthesis => ")",
+            Token::Not => "!",
+            Token::And => "&&",
+            Token::Or => "||",
+            Token::Less => "<",
+            Token::LessOrEqual => "<=",
+            Token::Greater => ">",
+            Token::GreaterOrEqual => ">=",
+            Token::Equal => "==",
+            Token::NotEqual => "!=",
+        };
+
+        format!("`{s}`")
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, Range<usize>)>, ParseError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let number = text.parse().map_err(|_| ParseError {
+                    message: format!("`{text}` is not a valid number"),
+                    span: start..i,
+                })?;
+                tokens.push((Token::Number(number), start..i));
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let token = match text.as_str() {
+                    "where" => Token::Where,
+                    _ => Token::Identifier(text),
+                };
+                tokens.push((token, start..i));
+                continue;
+            }
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '(' => Token::LeftParenthesis,
+            ')' => Token::RightParenthesis,
+            '<' if next == Some('=') => Token::LessOrEqual,
+            '<' => Token::Less,
+            '>' if next == Some('=') => Token::GreaterOrEqual,
+            '>' => Token::Greater,
+            '!' if next == Some('=') => Token::NotEqual,
+            '!' => Token::Not,
+            '=' if next == Some('=') => Token::Equal,
+            '&' if next == Some('&') => Token::And,
+            '|' if next == Some('|') => Token::Or,
+            '=' => {
+                return Err(ParseError {
+                    message: String::from("unexpected `=`, use `==` to compare values"),
+                    span: start..start + 1,
+                });
+            }
+            '&' | '|' => {
+                return Err(ParseError {
+                    message: format!("unexpected `{c}`, use `{c}{c}` instead"),
+                    span: start..start + 1,
+                });
+            }
+            c => {
+                return Err(ParseError {
+                    message: format!("unexpected character `{c}`"),
+                    span: start..start + 1,
+                });
+            }
+        };
+
+        i += match token {
+            Token::LessOrEqual
+            | Token::GreaterOrEqual
+            | Token::NotEqual
+            | Token::Equal
+            | Token::And
+            | Token::Or => 2,
+            _ => 1,
+        };
+        tokens.push((token, start..i));
+    }
+
+    Ok(tokens)
+}
+
+/// The deepest an `Expression` can be nested, counting parentheses, unary operators and chained binary operators.
+///
+/// Parsing and evaluating are both recursive, so this keeps a pasted run of `(((…` from overflowing the stack.
+const MAX_NESTING: usize = 256;
+
+/// A recursive descent parser over the tokens of an `Expression`, from the lowest precedence to the highest.
+struct Parser {
+    tokens: Vec<(Token, Range<usize>)>,
+    position: usize,
+    source_len: usize,
+    /// How deeply the node being parsed is nested.
+    nesting: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(t, _)| t)
+    }
+
+    fn error_here(&self, message: String) -> ParseError {
+        let span = match self.tokens.get(self.position) {
+            Some((_, span)) => span.clone(),
+            None => self.source_len..self.source_len,
+        };
+
+        ParseError { message, span }
+    }
+
+    /// Goes `levels` levels deeper, failing if that is deeper than `MAX_NESTING`.
+    fn nest(&mut self, levels: usize) -> Result<(), ParseError> {
+        self.nesting += levels;
+        match self.nesting > MAX_NESTING {
+            true => Err(self.error_here(format!(
+                "the objective is nested more than {MAX_NESTING} levels deep"
+            ))),
+            false => Ok(()),
+        }
+    }
+
+    /// Consumes the next token and returns the matching operator if it is one of `operators`.
+    fn next_operator(&mut self, operators: &[(Token, BinaryOperator)]) -> Option<BinaryOperator> {
+        let token = self.peek()?;
+        let operator = operators
+            .iter()
+            .find(|(t, _)| t == token)
+            .map(|(_, o)| *o)?;
+        self.position += 1;
+
+        Some(operator)
+    }
+
+    fn parse_binary(
+        &mut self,
+        operators: &[(Token, BinaryOperator)],
+        operand: fn(&mut Self) -> Result<Node, ParseError>,
+    ) -> Result<Node, ParseError> {
+        let mut node = operand(self)?;
+        // Every operator nests the chain before it one level deeper.
+        let mut chain = 0;
+        while let Some(operator) = self.next_operator(operators) {
+            chain += 1;
+            self.nest(1)?;
+            node = Node::Binary(operator, Box::new(node), Box::new(operand(self)?));
+        }
+        self.nesting -= chain;
+
+        Ok(node)
+    }
+
+    fn parse_or(&mut self) -> Result<Node, ParseError> {
+        self.parse_binary(&[(Token::Or, BinaryOperator::Or)], Self::parse_and)
+    }
+
+    fn parse_and(&mut self) -> Result<Node, ParseError> {
+        self.parse_binary(&[(Token::And, BinaryOperator::And)], Self::parse_comparison)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Node, ParseError> {
+        let node = self.parse_sum()?;
+        let operators = [
+            (Token::Less, BinaryOperator::Less),
+            (Token::LessOrEqual, BinaryOperator::LessOrEqual),
+            (Token::Greater, BinaryOperator::Greater),
+            (Token::GreaterOrEqual, BinaryOperator::GreaterOrEqual),
+            (Token::Equal, BinaryOperator::Equal),
+            (Token::NotEqual, BinaryOperator::NotEqual),
+        ];
+        match self.next_operator(&operators) {
+            Some(operator) => Ok(Node::Binary(
+                operator,
+                Box::new(node),
+                Box::new(self.parse_sum()?),
+            )),
+            None => Ok(node),
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<Node, ParseError> {
+        self.parse_binary(
+            &[
+                (Token::Plus, BinaryOperator::Add),
+                (Token::Minus, BinaryOperator::Subtract),
+            ],
+            Self::parse_product,
+        )
+    }
+
+    fn parse_product(&mut self) -> Result<Node, ParseError> {
+        self.parse_binary(
+            &[
+                (Token::Star, BinaryOperator::Multiply),
+                (Token::Slash, BinaryOperator::Divide),
+            ],
+            Self::parse_unary,
+        )
+    }
+
+    fn parse_unary(&mut self) -> Result<Node, ParseError> {
+        match self.peek() {
+            Some(Token::Minus) => {
+                self.position += 1;
+                self.nest(1)?;
+                let node = Node::Negate(Box::new(self.parse_unary()?));
+                self.nesting -= 1;
+                Ok(node)
+            }
+            Some(Token::Not) => {
+                self.position += 1;
+                self.nest(1)?;
+                let node = Node::Not(Box::new(self.parse_unary()?));
+                self.nesting -= 1;
+                Ok(node)
+            }
+            _ => self.parse_atom(),
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, ParseError> {
+        let Some((token, span)) = self.tokens.get(self.position).cloned() else {
+            return Err(self.error_here(String::from(
+                "expected a number, a variable or `(`, found the end of the objective",
+            )));
+        };
+
+        let node = match token {
+            Token::Number(n) => Node::Number(n),
+            Token::Identifier(name) => match Variable::from_name(&name) {
+                Some(variable) => Node::Variable(variable),
+                None => {
+                    let message = match suggest(&name) {
+                        Some(suggestion) => {
+                            format!("unknown variable `{name}`, did you mean `{suggestion}`?")
+                        }
+                        None => format!("unknown variable `{name}`"),
+                    };
+                    return Err(ParseError { message, span });
+                }
+            },
+            Token::LeftParenthesis => {
+                self.position += 1;
+                self.nest(1)?;
+                let node = self.parse_or()?;
+                self.nesting -= 1;
+                if self.peek() != Some(&Token::RightParenthesis) {
+                    return Err(ParseError {
+                        message: String::from("this `(` is never closed"),
+                        span,
+                    });
+                }
+                node
+            }
+            token => {
+                return Err(self.error_here(format!(
+                    "expected a number, a variable or `(`, found {}",
+                    token.describe()
+                )));
+            }
+        };
+        self.position += 1;
+
+        Ok(node)
+    }
+}
+
+/// Returns the name of the `Variable` closest to `name`, if any is close enough to be a likely typo.
+fn suggest(name: &str) -> Option<Variable> {
+    let name = normalize_name(name);
+    Variable::all()
+        .map(|v| (edit_distance(&name, &normalize_name(&v.to_string())), v))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, v)| v)
+}
+
+/// Returns the Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    row[b.len()]
+}
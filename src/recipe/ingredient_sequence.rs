@@ -0,0 +1,71 @@
+use crate::ingredients::Intermediate;
+
+/// The most ingredients a `Recipe` can hold.
+pub const MAX_INGREDIENTS: usize = 32;
+
+/// A sequence of at most `MAX_INGREDIENTS` ingredients stored inline, so that cloning a `Recipe` never allocates.
+#[derive(Clone, Copy)]
+pub(super) struct IngredientSequence {
+    len: u8,
+    /// Only the first `len` items are part of the sequence, the rest is filler.
+    items: [Intermediate; MAX_INGREDIENTS],
+}
+
+impl Default for IngredientSequence {
+    fn default() -> Self {
+        Self {
+            len: 0,
+            items: [Intermediate::Addy; MAX_INGREDIENTS],
+        }
+    }
+}
+
+impl std::fmt::Debug for IngredientSequence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl std::ops::Deref for IngredientSequence {
+    type Target = [Intermediate];
+
+    fn deref(&self) -> &Self::Target {
+        &self.items[..self.len as usize]
+    }
+}
+
+impl std::ops::DerefMut for IngredientSequence {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.items[..self.len as usize]
+    }
+}
+
+impl IngredientSequence {
+    /// Returns whether no more ingredients can be added to this sequence.
+    pub fn is_full(&self) -> bool {
+        self.len as usize == MAX_INGREDIENTS
+    }
+
+    /// Adds `intermediate` to the end of this sequence.
+    ///
+    /// Panics if the sequence is full.
+    pub fn push(&mut self, intermediate: Intermediate) {
+        self.insert(self.len as usize, intermediate);
+    }
+
+    /// Inserts `intermediate` before the item at `index`, or at the end if `index` is the length of this sequence.
+    ///
+    /// Panics if the sequence is full or `index` is out of bounds.
+    pub fn insert(&mut self, index: usize, intermediate: Intermediate) {
+        assert!(
+            !self.is_full(),
+            "a recipe can't have more than {MAX_INGREDIENTS} ingredients"
+        );
+        let len = self.len as usize;
+        assert!(index <= len, "insertion index {index} is out of bounds");
+
+        self.items.copy_within(index..len, index + 1);
+        self.items[index] = intermediate;
+        self.len += 1;
+    }
+}
@@ -0,0 +1,181 @@
+use super::Recipe;
+use crate::{
+    effect::Effect,
+    expenses::Expenses,
+    ingredients::{Base, Intermediate},
+};
+use enumset::EnumSet;
+use std::sync::LazyLock;
+
+/// A built-in value to optimize recipes for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    ProfitMargin,
+    Profit,
+    SellPrice,
+    ProductionCost,
+}
+
+impl std::fmt::Display for Metric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Metric::ProfitMargin => String::from("Profit Margin"),
+            Metric::Profit => String::from("Profit"),
+            Metric::SellPrice => String::from("Sell Price"),
+            Metric::ProductionCost => String::from("Production Cost"),
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl Metric {
+    pub const ALL: &'static [Self] = &[
+        Metric::ProductionCost,
+        Metric::SellPrice,
+        Metric::Profit,
+        Metric::ProfitMargin,
+    ];
+
+    /// Calculates the value of this `Metric` for the given `Recipe`, where higher is always better.
+    pub fn value(&self, r: &Recipe, expenses: Expenses) -> f32 {
+        match self {
+            Metric::ProfitMargin => r.profit_margin(expenses),
+            Metric::Profit => r.profit(expenses),
+            Metric::SellPrice => r.sell_price(),
+            Metric::ProductionCost => r.production_cost(expenses),
+        }
+    }
+
+    /// Returns an upper bound of `value` over every recipe made by adding at most `steps` ingredients to `r`.
+    ///
+    /// `effects` must be the effects of `r`.
+    pub fn upper_bound(
+        &self,
+        r: &Recipe,
+        effects: EnumSet<Effect>,
+        steps: usize,
+        expenses: Expenses,
+    ) -> f32 {
+        // Ingredients are never free, so the production cost can only go down from here.
+        let production_cost = r.production_cost(expenses);
+        let sell_price = max_sell_price(r.base(), effects, steps);
+
+        match self {
+            Metric::ProfitMargin => 1.0 + production_cost / sell_price,
+            Metric::Profit => sell_price + production_cost,
+            Metric::SellPrice => sell_price,
+            Metric::ProductionCost => production_cost,
+        }
+    }
+}
+
+/// A weighted sum of a `Metric` and the addictiveness of a recipe, used to rank recipes.
+///
+/// Both weights must be non-negative so that `upper_bound` stays an upper bound.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scoring {
+    pub metric: Metric,
+    pub metric_weight: f32,
+    pub addictiveness_weight: f32,
+}
+
+impl From<Metric> for Scoring {
+    /// Creates a `Scoring` that only takes `metric` into account.
+    fn from(metric: Metric) -> Self {
+        Self {
+            metric,
+            metric_weight: 1.0,
+            addictiveness_weight: 0.0,
+        }
+    }
+}
+
+impl Scoring {
+    /// Calculates the score of the given `Recipe`, where higher is always better.
+    pub fn value(&self, r: &Recipe, expenses: Expenses) -> f32 {
+        self.metric_weight * self.metric.value(r, expenses)
+            + self.addictiveness_weight * r.addictiveness()
+    }
+
+    /// Returns an upper bound of `value` over every recipe made by adding at most `steps` ingredients to `r`.
+    ///
+    /// `effects` must be the effects of `r`.
+    pub fn upper_bound(
+        &self,
+        r: &Recipe,
+        effects: EnumSet<Effect>,
+        steps: usize,
+        expenses: Expenses,
+    ) -> f32 {
+        self.metric_weight * self.metric.upper_bound(r, effects, steps, expenses)
+            + self.addictiveness_weight * max_addictiveness(r.base(), effects, steps)
+    }
+}
+
+/// The number of steps after which every effect has gone through all the effects it can ever turn into.
+const MAX_TRANSITION_STEPS: usize = Effect::ALL.len();
+
+/// `REACHABLE_PRICE_MODIFIER[n][e]` is the highest price modifier among the effects that effect `e` can turn into within `n` steps, including itself.
+static REACHABLE_PRICE_MODIFIER: LazyLock<Vec<Vec<f32>>> =
+    LazyLock::new(|| reachable_table(Effect::price_modifier));
+
+/// `REACHABLE_ADDICTIVENESS[n][e]` is the highest addictiveness among the effects that effect `e` can turn into within `n` steps, including itself.
+static REACHABLE_ADDICTIVENESS: LazyLock<Vec<Vec<f32>>> =
+    LazyLock::new(|| reachable_table(Effect::addictiveness));
+
+/// Builds a table whose entry `[n][e]` is the highest value of `property` among the effects that effect `e` can turn into within `n` steps, including itself.
+fn reachable_table(property: fn(&Effect) -> f32) -> Vec<Vec<f32>> {
+    let mut table: Vec<Vec<f32>> = vec![Effect::ALL.iter().map(property).collect()];
+
+    for _ in 0..MAX_TRANSITION_STEPS {
+        let previous = table.last().expect("the table always has a first row");
+        let row = Effect::ALL
+            .iter()
+            .map(|from_effect| {
+                Intermediate::ALL
+                    .iter()
+                    .filter_map(|i| i.interaction(from_effect))
+                    .map(|to_effect| previous[to_effect as usize])
+                    .fold(previous[*from_effect as usize], f32::max)
+            })
+            .collect();
+        table.push(row);
+    }
+
+    table
+}
+
+fn reachable(table: &[Vec<f32>], effect: Effect, steps: usize) -> f32 {
+    table[steps.min(MAX_TRANSITION_STEPS)][effect as usize]
+}
+
+/// Returns the sum of the 8 highest values of `table` that a recipe with the given effects can end up with after adding at most `steps` ingredients.
+///
+/// Every effect of the final recipe either comes from one of the current effects or from one of the added ingredients, and no more than 8 of them can be kept.
+/// Every value in `table` must be non-negative, as losing an effect is never accounted for.
+fn max_total_modifier(table: &[Vec<f32>], effects: EnumSet<Effect>, steps: usize) -> f32 {
+    let mut modifiers: Vec<f32> = effects.iter().map(|e| reachable(table, e, steps)).collect();
+
+    if steps > 0 {
+        let added_modifier = Intermediate::ALL
+            .iter()
+            .map(|i| reachable(table, i.effect(), steps - 1))
+            .fold(0.0, f32::max);
+        modifiers.extend(std::iter::repeat_n(added_modifier, steps.min(8)));
+    }
+
+    modifiers.sort_by(|a, b| b.total_cmp(a));
+    modifiers.iter().take(8).sum()
+}
+
+/// Returns an upper bound of the sell price of any recipe made by adding at most `steps` ingredients to a recipe with the given base and effects.
+fn max_sell_price(base: Base, effects: EnumSet<Effect>, steps: usize) -> f32 {
+    base.sell_price() * (1.0 + max_total_modifier(&REACHABLE_PRICE_MODIFIER, effects, steps))
+}
+
+/// Returns an upper bound of the addictiveness of any recipe made by adding at most `steps` ingredients to a recipe with the given base and effects.
+fn max_addictiveness(base: Base, effects: EnumSet<Effect>, steps: usize) -> f32 {
+    (base.addictiveness() + max_total_modifier(&REACHABLE_ADDICTIVENESS, effects, steps))
+        .clamp(f32::MIN_POSITIVE, 1.0)
+}
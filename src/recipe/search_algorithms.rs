@@ -1,69 +1,1365 @@
-use super::Recipe;
-use crate::ingredients::{Base, Intermediate};
+use super::{MAX_INGREDIENTS, Recipe, RecipeEdit, StartingState, metric::Scoring};
+use crate::{
+    effect::{Effect, EffectConstraints},
+    expenses::Expenses,
+    ingredients::{Base, Intermediate},
+};
+use ahash::AHashMap;
+use backward_space::BackwardSpace;
+pub use database::{DEFAULT_DATABASE_PATH, DatabaseError, ReachableDatabase};
+use enumset::EnumSet;
+pub use progress::{ProgressReporter, SearchProgress, SearchStats};
 use rayon::prelude::*;
+use state_space::StateSpace;
+use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::BinaryHeap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
+    time::{Duration, Instant},
+};
+pub use stream::{AllEffectSets, AllRecipes};
+pub use thread_pool::ThreadSettings;
+
+mod backward_space;
+mod database;
+mod progress;
+mod state_space;
+mod stream;
+mod thread_pool;
+
+/// A flag shared between a search and whoever started it, used to stop the search early.
+///
+/// Cancelled searches return promptly with the best result among the recipes they already went through.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Creates a `CancellationToken` that hasn't been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a token that is cancelled along with this one, and also once `deadline` has passed.
+    pub fn with_deadline(&self, deadline: Instant) -> Self {
+        Self {
+            cancelled: self.cancelled.clone(),
+            deadline: Some(self.deadline.map_or(deadline, |d| d.min(deadline))),
+        }
+    }
+
+    /// Signals every search holding this token to stop.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether `cancel` has been called on this token or any of its clones, or its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) || self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+}
+
+/// How a search picks one of several recipes that satisfy it equally well.
+///
+/// Searches that can break ties for free always use `Recipe::cmp_tie_break`, this only matters for the ones where it has a cost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Returns whichever recipe is found first, which can depend on thread scheduling and the number of threads.
+    Any,
+    /// Returns the same recipe on every run and every machine, at the cost of some parallelism.
+    Deterministic,
+}
+
+/// Compares two recipes alongside their values such that the one a search maximizing the values returns is `Greater`, breaking ties with `Recipe::cmp_tie_break`.
+fn cmp_scored<K: Ord>((a, a_key): &(Recipe, K), (b, b_key): &(Recipe, K)) -> CmpOrdering {
+    a_key.cmp(b_key).then_with(|| b.cmp_tie_break(a))
+}
+
+/// Lowers `depth` so that adding that many ingredients to a recipe that already has `length` never goes past `MAX_INGREDIENTS`.
+fn clamp_depth(depth: i8, length: usize) -> i8 {
+    depth.min(MAX_INGREDIENTS.saturating_sub(length) as i8)
+}
+
+/// A breadth-first search algorithm that goes through all effect sets reachable from `root` and returns the recipe with the maximum value computed by the given function.  
+///
+/// Recipes reaching the same effect set are collapsed into the cheapest one, so `f` must never prefer a more expensive recipe over a cheaper one with the same effects. Every built-in metric satisfies this.
+///
+/// The size of the search space is bounded by the number of distinct reachable effect sets rather than `16^depth`, which keeps depths up to `8` within seconds.
+pub fn search_for_recipe_max_bfs<K, F>(
+    root: Recipe,
+    f: F,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Recipe, SearchStats)
+where
+    K: Ord + Send,
+    F: Fn(&Recipe) -> K + Sync + std::marker::Send,
+{
+    let depth = clamp_depth(depth, root.intermediates().len());
+    let stats = progress.record_stats();
+    let space = StateSpace::explore(root, depth, allowed, cancel, progress);
+    let best: Vec<usize> = space.best().collect();
+    progress.evaluate(best.len() as u64);
+
+    let (recipe, _) = best
+        .into_par_iter()
+        .map(|i| {
+            let r = space.recipe(i);
+            let key = f(&r);
+            (r, key)
+        })
+        .max_by(cmp_scored)
+        .expect("the root is always part of the state space");
+
+    (recipe, stats.finish(Some(space.unique_effect_sets())))
+}
+
+/// A breadth-first search algorithm that goes through all effect sets reachable from `root` and returns the `k` recipes with the highest values computed by the given function, best first, alongside their values.  
+///
+/// Every returned recipe reaches a distinct effect set. The same restrictions on `f` as in `search_for_recipe_max_bfs` apply.
+/// Recipes with the same value are ordered with `Recipe::cmp_tie_break`.
+pub fn search_for_recipes_top_k_bfs<K, F>(
+    root: Recipe,
+    f: F,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    k: usize,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Vec<(Recipe, K)>, SearchStats)
+where
+    K: Ord + Send,
+    F: Fn(&Recipe) -> K + Sync + std::marker::Send,
+{
+    let depth = clamp_depth(depth, root.intermediates().len());
+    let stats = progress.record_stats();
+    let space = StateSpace::explore(root, depth, allowed, cancel, progress);
+    let best: Vec<usize> = space.best().collect();
+    progress.evaluate(best.len() as u64);
+
+    let mut scored_recipes: Vec<(Recipe, K)> = best
+        .into_par_iter()
+        .map(|i| {
+            let r = space.recipe(i);
+            let key = f(&r);
+            (r, key)
+        })
+        .collect();
+    scored_recipes.par_sort_by(|a, b| cmp_scored(b, a));
+    scored_recipes.truncate(k);
+
+    (
+        scored_recipes,
+        stats.finish(Some(space.unique_effect_sets())),
+    )
+}
+
+/// A breadth-first search algorithm that goes through all effect sets reachable from `root` and returns the Pareto frontier of the values computed by the given function, alongside those values.  
+///
+/// `f` returns one value per objective, all of which are maximized. A recipe is part of the frontier if no other recipe is at least as good in every objective and strictly better in at least one.
+/// Recipes with identical values in every objective are collapsed into the one preferred by `Recipe::cmp_tie_break`. The frontier is sorted in descending order of the objectives.
+///
+/// Recipes reaching the same effect set are only kept if they are shorter or cheaper than all others, so `f` must never prefer a longer and more expensive recipe over another with the same effects.
+pub fn search_for_recipes_pareto_bfs<F>(
+    root: Recipe,
+    f: F,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Vec<(Recipe, Vec<f32>)>, SearchStats)
+where
+    F: Fn(&Recipe) -> Vec<f32> + Sync + std::marker::Send,
+{
+    let depth = clamp_depth(depth, root.intermediates().len());
+    let stats = progress.record_stats();
+    let space = StateSpace::explore(root, depth, allowed, cancel, progress);
+    progress.evaluate(space.all().len() as u64);
+
+    let mut scored_recipes: Vec<(Recipe, Vec<f32>)> = space
+        .all()
+        .into_par_iter()
+        .map(|i| {
+            let r = space.recipe(i);
+            let values = f(&r);
+            (r, values)
+        })
+        .collect();
+    scored_recipes.par_sort_by(|(a_recipe, a), (b_recipe, b)| {
+        b.iter()
+            .zip(a)
+            .map(|(b, a)| b.total_cmp(a))
+            .find(|o| o.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a_recipe.cmp_tie_break(b_recipe))
+    });
+
+    // Points can only be dominated by points sorted before them, and domination is transitive, so checking against the frontier is enough.
+    let mut frontier: Vec<(Recipe, Vec<f32>)> = Vec::new();
+    for (r, values) in scored_recipes {
+        let dominated = frontier
+            .iter()
+            .any(|(_, other)| other.iter().zip(&values).all(|(o, v)| o >= v));
+        if !dominated {
+            frontier.push((r, values));
+        }
+    }
+
+    (frontier, stats.finish(Some(space.unique_effect_sets())))
+}
+
+/// A search algorithm that goes through every recipe made by inserting one allowed ingredient anywhere in `root` or replacing one of its ingredients with an allowed one, and returns the `k` best recipes with the highest values computed by the given function, best first, alongside the edit leading to them and their values.  
+///
+/// Recipes with the same value are ordered with `Recipe::cmp_tie_break`, and then in the order of `RecipeEdit::all`.
+///
+/// The search is reported to `progress` as a single stage, where every edit is one unit of work.
+pub fn search_for_recipes_top_k_single_edit<K, F>(
+    root: Recipe,
+    f: F,
+    allowed: EnumSet<Intermediate>,
+    k: usize,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Vec<(RecipeEdit, Recipe, K)>, SearchStats)
+where
+    K: Ord + Send,
+    F: Fn(&Recipe) -> K + Sync + std::marker::Send,
+{
+    let stats = progress.record_stats();
+    let edits = RecipeEdit::all(&root, allowed);
+    progress.begin_stages(1);
+    progress.add_work(edits.len() as u64);
+    progress.expand(1);
+
+    let mut scored_recipes: Vec<(RecipeEdit, Recipe, K)> = edits
+        .into_par_iter()
+        .filter(|_| !cancel.is_cancelled())
+        .map(|edit| {
+            let r = edit.apply(&root);
+            let key = f(&r);
+            progress.visit(1);
+            progress.evaluate(1);
+            progress.complete_work(1);
+            (edit, r, key)
+        })
+        .collect();
+    scored_recipes.par_sort_by(|(_, a_recipe, a), (_, b_recipe, b)| {
+        b.cmp(a).then_with(|| a_recipe.cmp_tie_break(b_recipe))
+    });
+    scored_recipes.truncate(k);
+    progress.complete_stage();
+
+    (scored_recipes, stats.finish(None))
+}
+
+/// A breadth-first search algorithm that goes through all effect sets reachable from every one of `starts` and returns the shortest recipe that satisfies the given predicate.  
+///
+/// Recipes reaching the same effect set are collapsed into the cheapest one, so `f` should only depend on the base, effects and cost of a recipe.
+/// When several of the shortest recipes satisfy `f`, the one preferred by `Recipe::cmp_tie_break` is returned.
+///
+/// The size of the search space is bounded by the number of distinct reachable effect sets rather than `16^depth`.
+pub fn search_for_recipe_find_bfs<F>(
+    starts: &[StartingState],
+    f: F,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Option<Recipe>, SearchStats)
+where
+    F: Fn(&Recipe) -> bool + Sync + std::marker::Send,
+{
+    let depth = clamp_depth(depth, 0);
+    let stats = progress.record_stats();
+    progress.begin_stages(depth.max(0) as u64);
+    let mut spaces: Vec<StateSpace> = starts
+        .iter()
+        .map(|s| StateSpace::new(Recipe::with_start(*s), allowed))
+        .collect();
+
+    let finish = |spaces: &[StateSpace]| {
+        stats.finish(Some(spaces.iter().map(|s| s.unique_effect_sets()).sum()))
+    };
+    loop {
+        progress.evaluate(spaces.iter().map(|s| s.frontier().len() as u64).sum());
+        let matching_recipe = spaces
+            .par_iter()
+            .flat_map(|space| {
+                space
+                    .frontier()
+                    .par_iter()
+                    .map(|i| space.recipe(*i))
+                    .filter(|r| f(r))
+            })
+            .min_by(Recipe::cmp_tie_break);
+        if matching_recipe.is_some() {
+            return (matching_recipe, finish(&spaces));
+        }
+
+        if spaces.first().is_none_or(|s| s.depth() >= depth) || cancel.is_cancelled() {
+            return (None, finish(&spaces));
+        }
+        spaces
+            .par_iter_mut()
+            .for_each(|space| space.expand(cancel, progress));
+        progress.complete_stage();
+    }
+}
+
+/// A recipe waiting to be settled by `search_for_recipe_find_cheapest_ucs`.
+struct Label {
+    /// The total cost of the recipe as a positive `f32`.
+    cost: f32,
+    depth: i8,
+    recipe: Recipe,
+}
+
+impl PartialEq for Label {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Label {}
+
+impl PartialOrd for Label {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Label {
+    /// Cheaper labels are greater so that they are popped first from a `BinaryHeap`, with ties broken by `Recipe::cmp_tie_break`.
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then(other.depth.cmp(&self.depth))
+            .then_with(|| other.recipe.cmp_tie_break(&self.recipe))
+    }
+}
+
+/// A uniform-cost search algorithm that goes through all effect sets reachable from every one of `starts` in order of increasing cost and returns the cheapest recipe with at most `depth` ingredients that satisfies the given predicate.  
+///
+/// The cost of a recipe is its production cost under the given `Expenses`. Recipes reaching the same effect set are only explored if they are shorter than every cheaper one, so `f` should only depend on the base and effects of a recipe.
+///
+/// Progress is only reported as the number of recipes gone through, as the amount of work left isn't known until a match is found.
+pub fn search_for_recipe_find_cheapest_ucs<F>(
+    starts: &[StartingState],
+    f: F,
+    expenses: Expenses,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Option<Recipe>, SearchStats)
+where
+    F: Fn(&Recipe) -> bool,
+{
+    let depth = clamp_depth(depth, 0);
+    let stats = progress.record_stats();
+    let mut queue: BinaryHeap<Label> = starts
+        .iter()
+        .map(|s| Label {
+            cost: -s.base.production_cost(expenses),
+            depth: 0,
+            recipe: Recipe::with_start(*s),
+        })
+        .collect();
+
+    // The length of the shortest settled recipe for every state.
+    // Since recipes are settled in order of cost, a later one is only worth exploring if it is shorter.
+    let mut settled: AHashMap<(Base, EnumSet<Effect>), i8> = AHashMap::new();
+    let finish =
+        |settled: &AHashMap<(Base, EnumSet<Effect>), i8>| stats.finish(Some(settled.len() as u64));
+    while let Some(label) = queue.pop() {
+        if cancel.is_cancelled() {
+            return (None, finish(&settled));
+        }
+
+        let state = (label.recipe.base(), label.recipe.calculate_effects());
+        if settled.get(&state).is_some_and(|d| *d <= label.depth) {
+            progress.prune(1);
+            continue;
+        }
+        settled.insert(state, label.depth);
+        progress.visit(1);
+        progress.evaluate(1);
+
+        if f(&label.recipe) {
+            return (Some(label.recipe), finish(&settled));
+        }
+        if label.depth >= depth {
+            continue;
+        }
+
+        progress.expand(1);
+        for i in allowed {
+            queue.push(Label {
+                cost: label.cost - i.purchase_price(),
+                depth: label.depth + 1,
+                recipe: label.recipe.clone().add_intermediate(i),
+            });
+        }
+    }
+
+    (None, finish(&settled))
+}
+
+/// A bidirectional breadth-first search algorithm that goes through effect sets forward from every one of `starts` and backward from every effect set satisfying `constraints` until the two meet, and returns the shortest recipe with at most `depth` ingredients that satisfies them.
+///
+/// The backward side walks every ingredient's interactions in reverse, and whichever side has the smaller frontier is expanded next, so a recipe of `n` ingredients is found once both sides have gone about `n / 2` steps deep.
+/// Long recipes for many required effects are found much faster than by `search_for_recipe_find_bfs`, which goes the full length forward.
+/// With few required effects so many effect sets satisfy `constraints` that the backward side is rarely expanded, and the search behaves like `search_for_recipe_find_bfs`.
+///
+/// Every shortest recipe goes through an effect set where the two sides meet, and only the cheapest way there and from there is kept on each side.
+/// When several of the shortest recipes satisfy `constraints`, the one preferred by `Recipe::cmp_tie_break` is returned, like in `search_for_recipe_find_bfs`.
+///
+/// Every expansion of either side is reported to `progress` as one stage.
+pub fn search_for_recipe_find_bidirectional(
+    starts: &[StartingState],
+    constraints: EffectConstraints,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Option<Recipe>, SearchStats) {
+    let depth = clamp_depth(depth, 0);
+    let stats = progress.record_stats();
+    progress.begin_stages(depth.max(0) as u64);
+    let mut forward: Vec<StateSpace> = starts
+        .iter()
+        .map(|s| StateSpace::new(Recipe::with_start(*s), allowed))
+        .collect();
+    // The first node reaching every effect set from every start, which is the cheapest of the shortest recipes reaching it.
+    let mut reached: Vec<AHashMap<EnumSet<Effect>, usize>> = forward
+        .iter()
+        .map(|space| {
+            let root = space.frontier()[0];
+            AHashMap::from([(space.effects(root), root)])
+        })
+        .collect();
+    // The backward side is only built once it is expanded, as there may be a huge number of goals.
+    let mut backward: Option<BackwardSpace> = None;
+
+    let finish = |forward: &[StateSpace], backward: &Option<BackwardSpace>| {
+        let forward_sets: u64 = forward.iter().map(|s| s.unique_effect_sets()).sum();
+        let backward_sets = backward.as_ref().map_or(0, |b| b.unique_effect_sets());
+        stats.finish(Some(forward_sets + backward_sets))
+    };
+
+    // The nodes of the forward side that reach an effect set found by the backward side, as pairs of a start and a node.
+    progress.evaluate(forward.len() as u64);
+    let mut meetings: Vec<(usize, usize)> = forward
+        .iter()
+        .enumerate()
+        .filter(|(_, space)| constraints.is_satisfied_by(space.effects(space.frontier()[0])))
+        .map(|(s, space)| (s, space.frontier()[0]))
+        .collect();
+    loop {
+        if !meetings.is_empty() {
+            let recipe = meetings
+                .iter()
+                .map(|&(s, node)| {
+                    let path = match &backward {
+                        Some(backward) => backward
+                            .path(forward[s].effects(node))
+                            .expect("meetings are reached by both sides"),
+                        None => Vec::new(),
+                    };
+                    path.into_iter()
+                        .fold(forward[s].recipe(node), Recipe::add_intermediate)
+                })
+                .min_by(Recipe::cmp_tie_break);
+            return (recipe, finish(&forward, &backward));
+        }
+
+        let forward_depth = forward.first().map_or(0, |s| s.depth());
+        let backward_depth = backward.as_ref().map_or(0, |b| b.depth());
+        if forward_depth + backward_depth >= depth || cancel.is_cancelled() {
+            return (None, finish(&forward, &backward));
+        }
+
+        let forward_size: u64 = forward.iter().map(|s| s.frontier().len() as u64).sum();
+        let backward_size = match &backward {
+            Some(backward) => backward.frontier().len() as u64,
+            None => BackwardSpace::goal_count(constraints),
+        };
+        if forward_size == 0 && backward_size == 0 {
+            return (None, finish(&forward, &backward));
+        }
+
+        if backward_size != 0 && (forward_size == 0 || backward_size < forward_size) {
+            let backward = backward.get_or_insert_with(|| BackwardSpace::new(constraints, allowed));
+            backward.expand(cancel, progress);
+            progress.evaluate(backward.frontier().len() as u64 * reached.len() as u64);
+            meetings = reached
+                .iter()
+                .enumerate()
+                .flat_map(|(s, reached)| {
+                    backward
+                        .frontier()
+                        .iter()
+                        .filter_map(move |effects| Some((s, *reached.get(effects)?)))
+                })
+                .collect();
+        } else {
+            forward
+                .par_iter_mut()
+                .for_each(|space| space.expand(cancel, progress));
+            let is_goal = |effects: EnumSet<Effect>| match &backward {
+                Some(backward) => backward.distance(effects).is_some(),
+                None => constraints.is_satisfied_by(effects),
+            };
+            meetings.clear();
+            for (s, (space, reached)) in forward.iter().zip(&mut reached).enumerate() {
+                progress.evaluate(space.frontier().len() as u64);
+                for &node in space.frontier() {
+                    let effects = space.effects(node);
+                    if reached.contains_key(&effects) {
+                        continue;
+                    }
+                    reached.insert(effects, node);
+                    if is_goal(effects) {
+                        meetings.push((s, node));
+                    }
+                }
+            }
+        }
+        progress.complete_stage();
+    }
+}
+
+/// The recipes found by `search_for_recipes_batch_bfs` for one target and one start.
+#[derive(Clone, Debug, Default)]
+pub struct TargetRecipes {
+    /// The shortest recipe satisfying the target, ties going to the cheapest one.
+    pub shortest: Option<Recipe>,
+    /// The cheapest recipe satisfying the target, ties going to the shortest one.
+    pub cheapest: Option<Recipe>,
+}
+
+/// A breadth-first search algorithm that goes through all effect sets reachable from every one of `starts` once and returns the shortest and the cheapest recipe from every start that satisfies each of `targets`.  
+///
+/// The result has one entry per target, each holding one `TargetRecipes` per start in the order of `starts`.
+/// Every start is explored up to `depth` ingredients a single time, and every target is then looked up in the explored states, so a long list of targets costs little more than a single one.
+/// Remaining ties are broken by the first recipe in lexicographic order of ingredients.
+///
+/// Every expansion of every start is reported to `progress` as one stage.
+///
+/// The size of the search space is bounded by the number of distinct reachable effect sets rather than `16^depth`.
+pub fn search_for_recipes_batch_bfs(
+    starts: &[StartingState],
+    targets: &[EffectConstraints],
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Vec<Vec<TargetRecipes>>, SearchStats) {
+    let depth = clamp_depth(depth, 0);
+    let stats = progress.record_stats();
+    progress.begin_stages(starts.len() as u64 * depth.max(0) as u64);
+    let mut results = vec![vec![TargetRecipes::default(); starts.len()]; targets.len()];
+    let mut unique_effect_sets = 0;
+    for (s, start) in starts.iter().enumerate() {
+        let mut space = StateSpace::new(Recipe::with_start(*start), allowed);
+        // Nodes are admitted one layer at a time, so the length of a recipe is the number of layers ending before its node.
+        let mut layer_ends = Vec::new();
+        while space.depth() < depth && !cancel.is_cancelled() {
+            layer_ends.push(space.all().end);
+            space.expand(cancel, progress);
+            progress.complete_stage();
+        }
+        if cancel.is_cancelled() {
+            break;
+        }
+        unique_effect_sets += space.unique_effect_sets();
+
+        let length = |index: usize| layer_ends.partition_point(|&end| end <= index);
+        progress.evaluate(space.all().len() as u64 * targets.len() as u64);
+        let found: Vec<TargetRecipes> = targets
+            .par_iter()
+            .map(|target| {
+                let matches = space
+                    .all()
+                    .filter(|&i| target.is_satisfied_by(space.effects(i)));
+                // Costs are negative, so the cheapest node has the highest one.
+                let shortest = matches.clone().min_by(|&a, &b| {
+                    length(a)
+                        .cmp(&length(b))
+                        .then(space.cost(b).total_cmp(&space.cost(a)))
+                        .then(a.cmp(&b))
+                });
+                let cheapest = matches.min_by(|&a, &b| {
+                    space
+                        .cost(b)
+                        .total_cmp(&space.cost(a))
+                        .then(length(a).cmp(&length(b)))
+                        .then(a.cmp(&b))
+                });
+                TargetRecipes {
+                    shortest: shortest.map(|i| space.recipe(i)),
+                    cheapest: cheapest.map(|i| space.recipe(i)),
+                }
+            })
+            .collect();
+        for (result, found) in results.iter_mut().zip(found) {
+            result[s] = found;
+        }
+    }
+
+    (results, stats.finish(Some(unique_effect_sets)))
+}
 
 /// A brute-force DFS search algorithm that goes through all possible recipes starting from `root` and returns the recipe with the maximum value computed by the given function.  
 ///
+/// When several recipes have the maximum value, the one preferred by `Recipe::cmp_tie_break` is returned.
+/// Every top-level branch is reported to `progress` as one stage.
+///
 /// The size of the search space is about `16^depth`. Depths greater than `5` tend to produce exponentially unreasonable search times.
-pub fn search_for_recipe_max_dfs<K, F>(root: Recipe, f: F, depth: i8) -> Recipe
+pub fn search_for_recipe_max_dfs<K, F>(
+    root: Recipe,
+    f: F,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Recipe, SearchStats)
 where
     K: Ord + Send,
     F: Fn(&Recipe) -> K + Sync + std::marker::Send + Clone,
 {
-    if depth <= 0 {
-        return root;
+    let depth = clamp_depth(depth, root.intermediates().len());
+    let stats = progress.record_stats();
+    progress.begin_stages(allowed.len() as u64);
+    progress.visit(1);
+    progress.evaluate(1);
+    if depth <= 0 || cancel.is_cancelled() {
+        return (root, stats.finish(None));
     }
 
-    let best_recipe: Recipe = Intermediate::ALL
+    progress.expand(1);
+    let (recipe, _) = Intermediate::ALL
         .par_iter()
-        .map(|i| search_for_recipe_max_dfs(root.clone().add_intermediate(*i), f.clone(), depth - 1))
-        .chain([root.clone()])
-        .max_by_key(f.clone())
+        .filter(|i| allowed.contains(**i))
+        .map(|i| {
+            let r = max_dfs(
+                root.clone().add_intermediate(*i),
+                f.clone(),
+                depth - 1,
+                allowed,
+                cancel,
+                progress,
+            );
+            progress.complete_stage();
+            r
+        })
+        .chain([(root.clone(), f(&root))])
+        .max_by(cmp_scored)
         .expect("PANIC AAAAHHHH");
 
-    best_recipe
+    (recipe, stats.finish(None))
+}
+
+fn max_dfs<K, F>(
+    root: Recipe,
+    f: F,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Recipe, K)
+where
+    K: Ord + Send,
+    F: Fn(&Recipe) -> K + Sync + std::marker::Send + Clone,
+{
+    progress.visit(1);
+    progress.evaluate(1);
+    if depth <= 0 || cancel.is_cancelled() {
+        let key = f(&root);
+        return (root, key);
+    }
+    progress.expand(1);
+
+    Intermediate::ALL
+        .par_iter()
+        .filter(|i| allowed.contains(**i))
+        .map(|i| {
+            max_dfs(
+                root.clone().add_intermediate(*i),
+                f.clone(),
+                depth - 1,
+                allowed,
+                cancel,
+                progress,
+            )
+        })
+        .chain([(root.clone(), f(&root))])
+        .max_by(cmp_scored)
+        .expect("PANIC AAAAHHHH")
+}
+
+/// The shared state of a branch and bound search.
+struct BranchAndBound<'a> {
+    scoring: Scoring,
+    expenses: Expenses,
+    allowed: EnumSet<Intermediate>,
+    /// The value of the best recipe found so far, stored with `ordered_bits` so that it can be compared atomically.
+    best_value: AtomicU32,
+    /// The best recipe found so far alongside its value, which is only updated under the lock so that ties are broken consistently.
+    best_recipe: Mutex<(Recipe, u32)>,
+    /// Called with every recipe that becomes the best one found so far.
+    on_improvement: &'a (dyn Fn(&Recipe) + Sync),
+    cancel: &'a CancellationToken,
+    progress: &'a ProgressReporter,
+}
+
+/// Maps an `f32` to a `u32` such that the order of non-NaN values is preserved.
+fn ordered_bits(value: f32) -> u32 {
+    let bits = value.to_bits();
+    match bits >> 31 {
+        0 => bits | (1 << 31),
+        _ => !bits,
+    }
+}
+
+/// The relative amount by which the upper bound of a branch is raised before comparing it to the best value, to make up for floating point errors in the bound.
+const BOUND_TOLERANCE: f32 = 1e-4;
+
+/// An exact branch and bound DFS search algorithm that goes through all possible recipes starting from `root` and returns the recipe with the maximum value of the given `Scoring`, alongside statistics about the search.  
+///
+/// Returns a recipe with the same value as `search_for_recipe_max_dfs` would, but skips every branch whose `Scoring::upper_bound` can't beat the best recipe found so far.
+/// When several recipes have the best value, the one preferred by `Recipe::cmp_tie_break` is returned.
+/// Every top-level branch is reported to `progress` as one stage.
+pub fn search_for_recipe_max_branch_and_bound(
+    root: Recipe,
+    scoring: Scoring,
+    expenses: Expenses,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Recipe, SearchStats) {
+    let depth = clamp_depth(depth, root.intermediates().len());
+    let stats = progress.record_stats();
+    let incumbent = root.clone();
+    let recipe = branch_and_bound(
+        root,
+        incumbent,
+        scoring,
+        expenses,
+        depth,
+        allowed,
+        &|_| {},
+        cancel,
+        progress,
+    );
+
+    (recipe, stats.finish(None))
+}
+
+/// Runs a branch and bound search from `root` that only looks for recipes strictly better than `incumbent`, returning `incumbent` if there are none.
+#[expect(clippy::too_many_arguments)]
+fn branch_and_bound(
+    root: Recipe,
+    incumbent: Recipe,
+    scoring: Scoring,
+    expenses: Expenses,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    on_improvement: &(dyn Fn(&Recipe) + Sync),
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> Recipe {
+    let root_value = ordered_bits(scoring.value(&root, expenses));
+    let incumbent_value = ordered_bits(scoring.value(&incumbent, expenses));
+    let best = std::cmp::max_by(
+        (incumbent, incumbent_value),
+        (root.clone(), root_value),
+        cmp_scored,
+    );
+    let search = BranchAndBound {
+        scoring,
+        expenses,
+        allowed,
+        best_value: AtomicU32::new(best.1),
+        best_recipe: Mutex::new(best),
+        on_improvement,
+        cancel,
+        progress,
+    };
+
+    progress.begin_stages(allowed.len() as u64);
+    progress.visit(1);
+    progress.evaluate(1);
+    if depth > 0 {
+        progress.expand(1);
+        let effects = root.calculate_effects();
+        Intermediate::ALL
+            .par_iter()
+            .filter(|i| allowed.contains(**i))
+            .for_each(|i| {
+                search.branch(&root, effects, *i, depth - 1);
+                progress.complete_stage();
+            });
+    }
+
+    let (recipe, _) = search
+        .best_recipe
+        .into_inner()
+        .expect("the best recipe is never held across a panic");
+
+    recipe
+}
+
+impl BranchAndBound<'_> {
+    /// Adds `intermediate` to `parent` and explores all recipes starting with the result that add at most `depth` more ingredients.
+    fn branch(
+        &self,
+        parent: &Recipe,
+        parent_effects: EnumSet<Effect>,
+        intermediate: Intermediate,
+        depth: i8,
+    ) {
+        if self.cancel.is_cancelled() {
+            return;
+        }
+
+        let recipe = parent.clone().add_intermediate(intermediate);
+        let mut effects = parent_effects;
+        intermediate.apply_to_effect_set(&mut effects);
+        self.progress.visit(1);
+        self.progress.evaluate(1);
+
+        let value = ordered_bits(self.scoring.value(&recipe, self.expenses));
+        if self.best_value.fetch_max(value, Ordering::Relaxed) <= value {
+            let mut best = self
+                .best_recipe
+                .lock()
+                .expect("the best recipe is never held across a panic");
+            // Another thread could have found something better between the two operations, and equally good recipes are only taken if they win the tie.
+            let candidate = (recipe.clone(), value);
+            if cmp_scored(&candidate, &best).is_gt() {
+                *best = candidate;
+                (self.on_improvement)(&recipe);
+            }
+        }
+
+        if depth <= 0 {
+            return;
+        }
+
+        let bound = self
+            .scoring
+            .upper_bound(&recipe, effects, depth as usize, self.expenses);
+        let bound = bound + BOUND_TOLERANCE * bound.abs().max(1.0);
+        if ordered_bits(bound) <= self.best_value.load(Ordering::Relaxed) {
+            self.progress.prune(1);
+            return;
+        }
+
+        self.progress.expand(1);
+        Intermediate::ALL
+            .par_iter()
+            .filter(|i| self.allowed.contains(**i))
+            .for_each(|i| self.branch(&recipe, effects, *i, depth - 1));
+    }
+}
+
+/// The depth at which `search_for_recipe_max_anytime` stops deepening if it still has time left.
+const MAX_ANYTIME_DEPTH: i8 = 20;
+
+/// An anytime search algorithm that runs `search_for_recipe_max_branch_and_bound` from `root` with increasing depths until `budget` runs out, and returns the best recipe found.  
+///
+/// Every search starts from the best recipe of the previous one, so most of the work that is repeated gets pruned right away.
+/// The result depends on how far the search got before `budget` ran out, so unlike the other searches it can differ between runs.
+/// `on_improvement` is called with every recipe that becomes the best one found so far, as soon as it is found.
+///
+/// The progress reported to `progress` is the fraction of `budget` that has elapsed.
+#[expect(clippy::too_many_arguments)]
+pub fn search_for_recipe_max_anytime<I>(
+    root: Recipe,
+    scoring: Scoring,
+    expenses: Expenses,
+    budget: Duration,
+    allowed: EnumSet<Intermediate>,
+    on_improvement: I,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Recipe, SearchStats)
+where
+    I: Fn(&Recipe) + Sync,
+{
+    let stats = progress.record_stats();
+    let cancel = cancel.with_deadline(Instant::now() + budget);
+    progress.begin_budget(budget);
+
+    let mut best = root.clone();
+    for depth in 1..=clamp_depth(MAX_ANYTIME_DEPTH, root.intermediates().len()) {
+        if cancel.is_cancelled() {
+            break;
+        }
+        best = branch_and_bound(
+            root.clone(),
+            best,
+            scoring,
+            expenses,
+            depth,
+            allowed,
+            &on_improvement,
+            &cancel,
+            progress,
+        );
+    }
+
+    (best, stats.finish(None))
 }
 
-/// A brute-force iterative deepening depth-first search algorithm that goes through all possible recipes starting from `root` and returns the first recipe that satifies the given predicate.  
+/// Runs the given search from an empty recipe of every `Base` in parallel and returns what it found for each, in the order of `Base::ALL`.  
+///
+/// Every base is reported to `progress` as one stage. Each search gets its own `ProgressReporter`, whose work is added to `progress` and to the returned statistics once it finishes.
+pub fn search_for_recipes_every_base<T, S>(
+    search: S,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Vec<(Base, T)>, SearchStats)
+where
+    T: Send,
+    S: Fn(Recipe, &CancellationToken, &ProgressReporter) -> T + Sync,
+{
+    let stats = progress.record_stats();
+    progress.begin_stages(Base::ALL.len() as u64);
+    let results = Base::ALL
+        .par_iter()
+        .map(|b| {
+            let base_progress = ProgressReporter::new();
+            let result = search(Recipe::with_base(*b), cancel, &base_progress);
+            progress.absorb(&base_progress);
+            progress.complete_stage();
+            (*b, result)
+        })
+        .collect();
+
+    (results, stats.finish(None))
+}
+
+/// A heuristic beam search algorithm that goes through recipes starting from `root` one ingredient at a time and returns the recipe with the maximum value computed by the given function.  
+///
+/// Only the `width` best recipes of every depth are expanded further, and recipes of the same depth reaching the same effect set are collapsed into the best one.
+/// Recipes with the same value are ranked with `Recipe::cmp_tie_break`.
+/// The result isn't guaranteed to be optimal, but the search space is bounded by `width * 16 * depth`, which keeps depths up to `20` within seconds.
+///
+/// Every depth is reported to `progress` as one stage.
+pub fn search_for_recipe_max_beam<K, F>(
+    root: Recipe,
+    f: F,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    width: usize,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Recipe, SearchStats)
+where
+    K: Ord + Send,
+    F: Fn(&Recipe) -> K + Sync + std::marker::Send,
+{
+    let depth = clamp_depth(depth, root.intermediates().len());
+    let stats = progress.record_stats();
+    progress.begin_stages(depth.max(0) as u64);
+    progress.visit(1);
+    progress.evaluate(1);
+    let root_key = f(&root);
+    let mut best = (root.clone(), root_key);
+    let mut beam = vec![root];
+
+    for _ in 0..depth {
+        if cancel.is_cancelled() || beam.is_empty() {
+            break;
+        }
+
+        progress.add_work(beam.len() as u64);
+        let children: Vec<(Recipe, EnumSet<Effect>, K)> = beam
+            .par_iter()
+            .filter(|_| !cancel.is_cancelled())
+            .flat_map_iter(|parent| {
+                progress.visit(allowed.len() as u64);
+                progress.expand(1);
+                progress.evaluate(allowed.len() as u64);
+                progress.complete_work(1);
+                allowed.iter().map(|i| {
+                    let r = parent.clone().add_intermediate(i);
+                    let key = f(&r);
+                    (r, key)
+                })
+            })
+            .map(|(r, key)| {
+                let effects = r.calculate_effects();
+                (r, effects, key)
+            })
+            .collect();
+        let child_count = children.len();
+
+        let mut layer: AHashMap<EnumSet<Effect>, (Recipe, K)> = AHashMap::new();
+        for (r, effects, key) in children {
+            let candidate = (r, key);
+            match layer.get(&effects) {
+                Some(other) if cmp_scored(other, &candidate).is_ge() => {}
+                _ => {
+                    layer.insert(effects, candidate);
+                }
+            }
+        }
+
+        let mut layer: Vec<(Recipe, K)> = layer.into_values().collect();
+        layer.par_sort_by(|a, b| cmp_scored(b, a));
+        layer.truncate(width);
+        progress.prune((child_count - layer.len()) as u64);
+
+        // The first recipe of the layer is its best, which keeps its key if it beats the best so far.
+        let mut layer = layer.into_iter();
+        beam = Vec::with_capacity(layer.len());
+        if let Some((r, key)) = layer.next() {
+            beam.push(r.clone());
+            if key > best.1 {
+                best = (r, key);
+            }
+        }
+        beam.extend(layer.map(|(r, _)| r));
+        progress.complete_stage();
+    }
+
+    (best.0, stats.finish(None))
+}
+
+/// The number of iterations between two checks of the `CancellationToken` in `search_for_recipe_max_annealing`.
+const ANNEALING_BATCH: u64 = 1000;
+
+/// The number of chains of `search_for_recipe_max_annealing` with `TieBreak::Deterministic`, which doesn't depend on the number of threads.
+const ANNEALING_DETERMINISTIC_CHAINS: u64 = 8;
+
+/// The number of random recipes used to pick the starting temperature of `search_for_recipe_max_annealing`.
+const ANNEALING_SAMPLES: usize = 64;
+
+/// A randomized simulated annealing search algorithm that goes through sequences of at most `depth` ingredients added to `root` and returns the recipe with the maximum value computed by the given function.  
+///
+/// Several chains repeatedly replace, insert, remove or swap a random ingredient, always accepting improvements and accepting worse recipes with a probability that shrinks over `iterations` iterations.
+/// The result isn't guaranteed to be optimal, but the time taken only depends on `iterations` and `depth`.
+///
+/// With `TieBreak::Any` there is one chain per thread, so the same `seed` only gives the same result on the same number of threads.
+/// With `TieBreak::Deterministic` there is always the same number of chains, so the same `seed` gives the same result on every machine.
+/// Equally good results of different chains are broken with `Recipe::cmp_tie_break`.
+///
+/// The search is reported to `progress` as a single stage.
+#[expect(clippy::too_many_arguments)]
+pub fn search_for_recipe_max_annealing<F>(
+    root: Recipe,
+    f: F,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    iterations: u64,
+    seed: u64,
+    tie_break: TieBreak,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Recipe, SearchStats)
+where
+    F: Fn(&Recipe) -> f32 + Sync + std::marker::Send,
+{
+    let depth = clamp_depth(depth, root.intermediates().len());
+    let stats = progress.record_stats();
+    let chains = match tie_break {
+        TieBreak::Any => rayon::current_num_threads() as u64,
+        TieBreak::Deterministic => ANNEALING_DETERMINISTIC_CHAINS,
+    };
+    progress.begin_stages(1);
+    progress.add_work(chains * iterations);
+
+    let ingredients: Vec<Intermediate> = allowed.iter().collect();
+    let depth = depth.max(0) as usize;
+    let build = |suffix: &[Intermediate]| {
+        suffix
+            .iter()
+            .fold(root.clone(), |r, i| r.add_intermediate(*i))
+    };
+
+    let root_value = f(&root);
+    if ingredients.is_empty() || depth == 0 {
+        return (root, stats.finish(None));
+    }
+
+    // Start at the typical difference between random recipes, so that most worse moves are accepted at first.
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let start_temperature = (0..ANNEALING_SAMPLES)
+        .map(|_| {
+            let length = rng.usize(1..=depth);
+            let sample: Vec<Intermediate> = (0..length)
+                .map(|_| ingredients[rng.usize(..ingredients.len())])
+                .collect();
+            (f(&build(&sample)) - root_value).abs()
+        })
+        .filter(|d| d.is_finite())
+        .sum::<f32>()
+        .max(f32::EPSILON)
+        / ANNEALING_SAMPLES as f32;
+    let end_temperature = start_temperature * 1e-3;
+
+    let (recipe, _) = (0..chains)
+        .into_par_iter()
+        .map(|chain| {
+            let mut rng = fastrand::Rng::with_seed(seed.wrapping_add(chain + 1));
+            let mut current: Vec<Intermediate> = Vec::new();
+            let mut current_value = root_value;
+            let mut best = (current.clone(), current_value);
+            for iteration in 0..iterations {
+                if iteration % ANNEALING_BATCH == 0 {
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    progress.visit(ANNEALING_BATCH.min(iterations - iteration));
+                    progress.evaluate(ANNEALING_BATCH.min(iterations - iteration));
+                    progress.complete_work(ANNEALING_BATCH.min(iterations - iteration));
+                }
+
+                let mut candidate = current.clone();
+                let ingredient = ingredients[rng.usize(..ingredients.len())];
+                match rng.u8(..4) {
+                    0 if !candidate.is_empty() => {
+                        let index = rng.usize(..candidate.len());
+                        candidate[index] = ingredient;
+                    }
+                    1 if candidate.len() < depth => {
+                        candidate.insert(rng.usize(..=candidate.len()), ingredient);
+                    }
+                    2 if !candidate.is_empty() => {
+                        candidate.remove(rng.usize(..candidate.len()));
+                    }
+                    3 if candidate.len() >= 2 => {
+                        let (a, b) = (rng.usize(..candidate.len()), rng.usize(..candidate.len()));
+                        candidate.swap(a, b);
+                    }
+                    _ if candidate.len() < depth => candidate.push(ingredient),
+                    _ => continue,
+                }
+
+                let candidate_value = f(&build(&candidate));
+                let progress_fraction = iteration as f32 / iterations as f32;
+                let temperature = start_temperature
+                    * (end_temperature / start_temperature).powf(progress_fraction);
+                let accepted = candidate_value >= current_value
+                    || rng.f32() < ((candidate_value - current_value) / temperature).exp();
+                if accepted {
+                    current = candidate;
+                    current_value = candidate_value;
+                    if current_value > best.1 {
+                        best = (current.clone(), current_value);
+                    }
+                }
+            }
+
+            (build(&best.0), best.1)
+        })
+        .max_by(|(a, a_value), (b, b_value)| {
+            a_value.total_cmp(b_value).then_with(|| b.cmp_tie_break(a))
+        })
+        .expect("there is always at least one chain");
+    progress.complete_stage();
+
+    (recipe, stats.finish(None))
+}
+
+/// A brute-force iterative deepening depth-first search algorithm that goes through all possible recipes starting from every one of `starts` and returns the first recipe that satifies the given predicate.  
 ///
 /// Differs from a traditional depth-first search in that it gradually increases the depth until it finds a match. This means that it mimics the search pattern of a breadth-first search while keeping the small memory footprint of a depth-first search.
 ///
+/// With `TieBreak::Deterministic` the returned recipe is the one with the fewest steps, then the first one in the order of `starts`, then the first one in lexicographic order of ingredients.
+///
+/// Every depth is reported to `progress` as one stage.
+///
 /// The size of the search space is about `16^depth`. Depths greater than `5` tend to produce exponentially unreasonable search times.
-pub fn search_for_recipe_find_iddfs<F>(f: F, depth: i8) -> Option<Recipe>
+pub fn search_for_recipe_find_iddfs<F>(
+    starts: &[StartingState],
+    f: F,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    tie_break: TieBreak,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Option<Recipe>, SearchStats)
 where
     F: Fn(&Recipe) -> bool + Sync + std::marker::Send,
 {
+    let depth = clamp_depth(depth, 0);
+    let stats = progress.record_stats();
+    progress.begin_stages(depth.max(0) as u64 + 1);
     for depth in 0..=depth {
-        let matching_recipe = Base::ALL
-            .iter()
-            .find_map(|b| search_for_recipe_find_dfs(Recipe::with_base(*b), &f, depth));
-        if let Some(r) = matching_recipe {
-            return Some(r);
+        let matching_recipe = starts.iter().find_map(|s| {
+            find_dfs(
+                Recipe::with_start(*s),
+                &f,
+                depth,
+                allowed,
+                tie_break,
+                cancel,
+                progress,
+            )
+        });
+        if matching_recipe.is_some() {
+            return (matching_recipe, stats.finish(None));
         }
+        progress.complete_stage();
     }
 
-    None
+    (None, stats.finish(None))
 }
 
 /// A brute-force depth-first search algorithm that goes through all possible recipes starting from `root` and returns the first recipe that satifies the given predicate.  
 ///
+/// With `TieBreak::Deterministic` the returned recipe is the first one in lexicographic order of ingredients, where every recipe comes before the ones continuing it.
+/// Branches are still searched in parallel, but a match can only be returned once every branch before it has been searched.
+///
+/// Every top-level branch is reported to `progress` as one stage.
+///
 /// The size of the search space is about `16^depth`. Depths greater than `5` tend to produce exponentially unreasonable search times.
-pub fn search_for_recipe_find_dfs<P>(root: Recipe, f: P, depth: i8) -> Option<Recipe>
+pub fn search_for_recipe_find_dfs<P>(
+    root: Recipe,
+    f: P,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    tie_break: TieBreak,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> (Option<Recipe>, SearchStats)
 where
     P: Fn(&Recipe) -> bool + Sync + std::marker::Send + Clone,
 {
+    let depth = clamp_depth(depth, root.intermediates().len());
+    let stats = progress.record_stats();
+    progress.begin_stages(allowed.len() as u64);
+    progress.visit(1);
+    progress.evaluate(1);
+    if cancel.is_cancelled() {
+        return (None, stats.finish(None));
+    }
+    if depth <= 0 {
+        return (f(&root).then_some(root), stats.finish(None));
+    }
+
+    progress.expand(1);
+    let branches = Intermediate::ALL
+        .par_iter()
+        .filter(|i| allowed.contains(**i))
+        .map(|i| {
+            let r = find_dfs(
+                root.clone().add_intermediate(*i),
+                f.clone(),
+                depth - 1,
+                allowed,
+                tie_break,
+                cancel,
+                progress,
+            );
+            progress.complete_stage();
+            r
+        });
+    let recipe = match tie_break {
+        TieBreak::Any => branches
+            .filter_map(|r| r)
+            .chain([root.clone()])
+            .find_any(f.clone()),
+        TieBreak::Deterministic if f(&root) => Some(root),
+        TieBreak::Deterministic => branches.find_map_first(|r| r),
+    };
+
+    (recipe, stats.finish(None))
+}
+
+fn find_dfs<P>(
+    root: Recipe,
+    f: P,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+    tie_break: TieBreak,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> Option<Recipe>
+where
+    P: Fn(&Recipe) -> bool + Sync + std::marker::Send + Clone,
+{
+    progress.visit(1);
+    progress.evaluate(1);
+    if cancel.is_cancelled() {
+        return None;
+    }
     if depth <= 0 {
         return f(&root).then_some(root);
     }
 
-    let best_recipe = Intermediate::ALL
+    progress.expand(1);
+    let branches = Intermediate::ALL
         .par_iter()
+        .filter(|i| allowed.contains(**i))
         .map(|i| {
-            search_for_recipe_find_dfs(root.clone().add_intermediate(*i), f.clone(), depth - 1)
-        })
-        .filter_map(|r| r)
-        .chain([root.clone()])
-        .find_any(f.clone());
+            find_dfs(
+                root.clone().add_intermediate(*i),
+                f.clone(),
+                depth - 1,
+                allowed,
+                tie_break,
+                cancel,
+                progress,
+            )
+        });
+    match tie_break {
+        TieBreak::Any => branches
+            .filter_map(|r| r)
+            .chain([root.clone()])
+            .find_any(f.clone()),
+        TieBreak::Deterministic if f(&root) => Some(root),
+        TieBreak::Deterministic => branches.find_map_first(|r| r),
+    }
+}
+
+/// A lazy iterative deepening depth-first search algorithm that goes through all possible recipes starting from `root` and yields every recipe that satisfies the given predicate.  
+///
+/// Recipes are yielded in order of length, then in lexicographic order of ingredients as in `Intermediate::ALL`, and nothing is searched until the next recipe is asked for.
+/// Like `search_for_recipe_find_iddfs`, every length starts over from `root`, so only the recipes leading to the current one are kept in memory.
+///
+/// The size of the search space is about `16^depth`. Depths greater than `5` tend to produce exponentially unreasonable search times if every recipe is collected.
+pub fn search_for_recipes_all_iddfs<P>(
+    root: Recipe,
+    f: P,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+) -> AllRecipes<P>
+where
+    P: Fn(&Recipe) -> bool,
+{
+    let depth = clamp_depth(depth, root.intermediates().len());
+    AllRecipes::new(root, f, depth, allowed)
+}
 
-    best_recipe
+/// A lazy breadth-first search algorithm that goes through all effect sets reachable from `root` and yields one recipe for every effect set that satisfies the given predicate.  
+///
+/// Every effect set is yielded once, by the cheapest of the shortest recipes reaching it, so `f` should only depend on the base, effects and cost of a recipe.
+/// Effect sets are yielded in order of the length of those recipes, then in lexicographic order of ingredients, and a new layer is only explored once the previous one has been yielded.
+///
+/// The size of the search space is bounded by the number of distinct reachable effect sets rather than `16^depth`.
+pub fn search_for_recipes_all_bfs<P>(
+    root: Recipe,
+    f: P,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+) -> AllEffectSets<P>
+where
+    P: Fn(&Recipe) -> bool,
+{
+    let depth = clamp_depth(depth, root.intermediates().len());
+    AllEffectSets::new(root, f, depth, allowed)
 }
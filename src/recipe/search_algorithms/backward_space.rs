@@ -0,0 +1,193 @@
+use super::{CancellationToken, ProgressReporter};
+use crate::{
+    effect::{Effect, EffectConstraints},
+    ingredients::Intermediate,
+    recipe::MAX_EFFECTS,
+};
+use ahash::AHashMap;
+use enumset::EnumSet;
+use rayon::prelude::*;
+
+/// An effect set from which a goal can be reached, with the first step of the way there.
+#[derive(Clone, Copy, Debug)]
+struct Link {
+    /// The number of ingredients needed to reach a goal.
+    distance: i8,
+    /// The summed purchase price of those ingredients, as a negative `f32`.
+    cost: f32,
+    /// The ingredient to mix in next and the effect set it leads to, or `None` for a goal.
+    next: Option<(Intermediate, EnumSet<Effect>)>,
+}
+
+/// The effect sets from which a set of goal effect sets can be reached, explored backwards from the goals.
+///
+/// Mixing doesn't depend on the base, so the same space serves every base.
+/// Only the cheapest way to a goal is kept for every effect set, among the shortest ones.
+pub(super) struct BackwardSpace {
+    links: AHashMap<EnumSet<Effect>, Link>,
+    frontier: Vec<EnumSet<Effect>>,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+}
+
+impl BackwardSpace {
+    /// Creates a `BackwardSpace` containing every effect set that satisfies `constraints`, which can only be expanded with `allowed` ingredients.
+    pub fn new(constraints: EffectConstraints, allowed: EnumSet<Intermediate>) -> Self {
+        let goals = match constraints.exact {
+            true => vec![constraints.required],
+            false => {
+                let optional: Vec<Effect> =
+                    (EnumSet::all() - constraints.required - constraints.forbidden)
+                        .iter()
+                        .collect();
+                let mut goals = Vec::new();
+                add_supersets(
+                    constraints.required,
+                    &optional,
+                    MAX_EFFECTS.saturating_sub(constraints.required.len()),
+                    &mut goals,
+                );
+                goals
+            }
+        };
+
+        let mut frontier: Vec<EnumSet<Effect>> = goals
+            .into_iter()
+            .filter(|g| g.len() <= MAX_EFFECTS)
+            .collect();
+        frontier.sort_unstable_by_key(|g| g.as_repr());
+        let goal = Link {
+            distance: 0,
+            cost: 0.0,
+            next: None,
+        };
+
+        Self {
+            links: frontier.iter().map(|g| (*g, goal)).collect(),
+            frontier,
+            depth: 0,
+            allowed,
+        }
+    }
+
+    /// Returns the number of effect sets satisfying `constraints`, which is the size of the frontier of a new `BackwardSpace`.
+    pub fn goal_count(constraints: EffectConstraints) -> u64 {
+        if constraints.exact {
+            return 1;
+        }
+        let optional =
+            (EnumSet::<Effect>::all() - constraints.required - constraints.forbidden).len() as u64;
+        let free = MAX_EFFECTS.saturating_sub(constraints.required.len()) as u64;
+
+        // The sum of the binomial coefficients `optional` choose `k` for every `k` up to `free`.
+        let mut binomial = 1;
+        let mut count = 1;
+        for k in 1..=free.min(optional) {
+            binomial = binomial * (optional + 1 - k) / k;
+            count += binomial;
+        }
+        count
+    }
+
+    /// Returns how many ingredients away from a goal the last expansion went.
+    pub fn depth(&self) -> i8 {
+        self.depth
+    }
+
+    /// Returns the effect sets that were admitted by the last call to `expand`, in increasing order of their masks.
+    pub fn frontier(&self) -> &[EnumSet<Effect>] {
+        &self.frontier
+    }
+
+    /// Returns the number of effect sets found so far.
+    pub fn unique_effect_sets(&self) -> u64 {
+        self.links.len() as u64
+    }
+
+    /// Returns the number of ingredients needed to reach a goal from `effects`, if it has been found.
+    pub fn distance(&self, effects: EnumSet<Effect>) -> Option<i8> {
+        self.links.get(&effects).map(|link| link.distance)
+    }
+
+    /// Returns the ingredients that lead from `effects` to a goal, if it has been found.
+    pub fn path(&self, mut effects: EnumSet<Effect>) -> Option<Vec<Intermediate>> {
+        let mut path = Vec::new();
+        while let Some((i, next)) = self.links.get(&effects)?.next {
+            path.push(i);
+            effects = next;
+        }
+        Some(path)
+    }
+
+    /// Adds every effect set that turns into one of the frontier with one more allowed ingredient and hasn't been found yet.
+    ///
+    /// When several ingredients lead to the same effect set, the one with the cheapest way to a goal is kept, with ties going to the first one in the order of the frontier and `Intermediate::ALL`.
+    /// If `cancel` is tripped midway, only part of the frontier gets expanded.
+    ///
+    /// Every effect set of the frontier is reported to `progress` as one unit of work, and every predecessor that isn't admitted as pruned.
+    pub fn expand(&mut self, cancel: &CancellationToken, progress: &ProgressReporter) {
+        progress.add_work(self.frontier.len() as u64);
+        let allowed = self.allowed;
+        let links = &self.links;
+        let predecessors: Vec<(EnumSet<Effect>, Link)> = self
+            .frontier
+            .par_iter()
+            .filter(|_| !cancel.is_cancelled())
+            .flat_map_iter(|&effects| {
+                progress.visit(allowed.len() as u64);
+                progress.expand(1);
+                progress.complete_work(1);
+                let link = links[&effects];
+                Intermediate::ALL
+                    .iter()
+                    .filter(move |i| allowed.contains(**i))
+                    .flat_map(move |i| {
+                        i.unapply_to_effect_set(effects)
+                            .into_iter()
+                            .map(move |predecessor| {
+                                let link = Link {
+                                    distance: link.distance + 1,
+                                    cost: link.cost + i.purchase_price(),
+                                    next: Some((*i, effects)),
+                                };
+                                (predecessor, link)
+                            })
+                    })
+            })
+            .collect();
+        let predecessor_count = predecessors.len();
+
+        let mut layer: AHashMap<EnumSet<Effect>, Link> = AHashMap::new();
+        for (predecessor, link) in predecessors {
+            if self.links.contains_key(&predecessor)
+                || layer.get(&predecessor).is_some_and(|l| l.cost >= link.cost)
+            {
+                continue;
+            }
+            layer.insert(predecessor, link);
+        }
+        progress.prune((predecessor_count - layer.len()) as u64);
+
+        self.frontier = layer.keys().copied().collect();
+        self.frontier
+            .sort_unstable_by_key(|effects| effects.as_repr());
+        self.links.extend(layer);
+        self.depth += 1;
+    }
+}
+
+/// Adds to `goals` every superset of `effects` with at most `free` more effects from `optional`.
+fn add_supersets(
+    effects: EnumSet<Effect>,
+    optional: &[Effect],
+    free: usize,
+    goals: &mut Vec<EnumSet<Effect>>,
+) {
+    goals.push(effects);
+    if free == 0 {
+        return;
+    }
+    for (index, effect) in optional.iter().enumerate() {
+        add_supersets(effects | *effect, &optional[index + 1..], free - 1, goals);
+    }
+}
@@ -0,0 +1,465 @@
+use super::{CancellationToken, ProgressReporter, StateSpace, cmp_scored};
+use crate::{
+    effect::Effect,
+    expenses::Expenses,
+    ingredients::{Base, Intermediate},
+    recipe::{MAX_EFFECTS, MAX_INGREDIENTS, Recipe, StartingState},
+};
+use ahash::AHashMap;
+use enumset::EnumSet;
+use rayon::prelude::*;
+use std::{io, path::Path};
+
+/// The bytes every database file starts with.
+const MAGIC: &[u8; 8] = b"S1MIXDB\0";
+
+/// The version of the database file format, bumped whenever it changes.
+pub const DATABASE_VERSION: u32 = 1;
+
+/// The path the database is saved to and loaded from when none is given.
+pub const DEFAULT_DATABASE_PATH: &str = "reachable_states.bin";
+
+/// The reason a database file couldn't be loaded.
+#[derive(Debug)]
+pub enum DatabaseError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// The file isn't a database.
+    NotADatabase,
+    /// The file was written by a different version of the calculator.
+    UnsupportedVersion(u32),
+    /// The file was built with different mixing rules or prices, and has to be built again.
+    OutdatedRules,
+    /// The file is truncated or its contents are invalid.
+    Corrupt,
+}
+
+impl std::fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            DatabaseError::Io(e) => format!("couldn't read the database: {e}"),
+            DatabaseError::NotADatabase => String::from("the file isn't a database"),
+            DatabaseError::UnsupportedVersion(v) => format!(
+                "the database has version {v}, but only version {DATABASE_VERSION} is supported"
+            ),
+            DatabaseError::OutdatedRules => {
+                String::from("the database was built with different mixing rules")
+            }
+            DatabaseError::Corrupt => String::from("the database is corrupt"),
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+impl From<io::Error> for DatabaseError {
+    fn from(e: io::Error) -> Self {
+        DatabaseError::Io(e)
+    }
+}
+
+/// Hashes everything the reachable states depend on, so that a database built with other rules can be told apart.
+fn rules_fingerprint() -> u64 {
+    // FNV-1a, which is stable across platforms and releases unlike `std::hash`.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut write = |value: u64| {
+        for byte in value.to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+
+    write(MAX_EFFECTS as u64);
+    for base in Base::ALL {
+        write(StartingState::from(*base).effects.as_u64());
+    }
+    for i in Intermediate::ALL {
+        write(i.purchase_price().to_bits() as u64);
+        write(i.apply_to_mask(0));
+        for e in Effect::ALL {
+            write(i.interaction(e).map_or(u64::MAX, |e| e as u64));
+        }
+    }
+
+    hash
+}
+
+/// The effect sets reachable from one fresh base, stored as the nodes of its `StateSpace`.
+struct ReachableGraph {
+    root: Recipe,
+    /// The parent and added ingredient of every node but the root, which is node `0`.
+    links: Vec<(u32, Intermediate)>,
+    effects: Vec<EnumSet<Effect>>,
+    /// The summed purchase price of the ingredients added since the root, as a negative `f32`.
+    costs: Vec<f32>,
+    /// The number of nodes with at most as many ingredients as the index.
+    layer_ends: Vec<usize>,
+    /// The cheapest node for every distinct effect set.
+    best: Vec<usize>,
+}
+
+impl ReachableGraph {
+    /// Builds the graph of `root` from the links of its nodes, which must come in the order they were admitted to the `StateSpace`.
+    fn from_links(root: Recipe, links: Vec<(u32, Intermediate)>) -> Result<Self, DatabaseError> {
+        let mut effects = vec![root.calculate_effects()];
+        let mut costs = vec![0.0];
+        let mut depths = vec![0];
+        let mut layer_ends = vec![];
+        for (index, &(parent, i)) in links.iter().enumerate() {
+            let parent = parent as usize;
+            // Parents come before their children and layers are stored one after another.
+            if parent > index || depths[parent] + 1 < *depths.last().unwrap() {
+                return Err(DatabaseError::Corrupt);
+            }
+            if depths[parent] + 1 > *depths.last().unwrap() {
+                layer_ends.push(depths.len());
+            }
+
+            effects.push(EnumSet::from_u64(i.apply_to_mask(effects[parent].as_u64())));
+            costs.push(costs[parent] + i.purchase_price());
+            depths.push(depths[parent] + 1);
+        }
+        layer_ends.push(depths.len());
+
+        let mut graph = Self {
+            root,
+            links,
+            effects,
+            costs,
+            layer_ends,
+            best: Vec::new(),
+        };
+        graph.best = graph.best_within(graph.depth());
+
+        Ok(graph)
+    }
+
+    /// Returns the number of ingredients of the longest recipes stored.
+    fn depth(&self) -> i8 {
+        self.layer_ends.len() as i8 - 1
+    }
+
+    /// Returns the nodes with at most `depth` ingredients.
+    fn within(&self, depth: i8) -> std::ops::Range<usize> {
+        0..self.layer_ends[depth.clamp(0, self.depth()) as usize]
+    }
+
+    /// Returns the nodes with exactly `depth` ingredients.
+    fn layer(&self, depth: i8) -> std::ops::Range<usize> {
+        match depth {
+            0 => self.within(0),
+            _ => self.within(depth - 1).end..self.within(depth).end,
+        }
+    }
+
+    /// Returns the cheapest node with at most `depth` ingredients for every distinct effect set.
+    fn best_within(&self, depth: i8) -> Vec<usize> {
+        // Nodes are only admitted if they are cheaper than every earlier one with the same effects, so the last one is the cheapest.
+        let mut best = AHashMap::new();
+        for index in self.within(depth) {
+            best.insert(self.effects[index], index);
+        }
+
+        let mut best: Vec<usize> = best.into_values().collect();
+        best.sort_unstable();
+        best
+    }
+
+    /// Returns `best` if `depth` covers the whole graph, or computes it for `depth`.
+    fn best(&self, depth: i8) -> std::borrow::Cow<'_, [usize]> {
+        match depth >= self.depth() {
+            true => std::borrow::Cow::Borrowed(&self.best),
+            false => std::borrow::Cow::Owned(self.best_within(depth)),
+        }
+    }
+
+    /// Rebuilds the `Recipe` leading to the node at `index`, reusing its stored effects instead of mixing again.
+    fn recipe(&self, index: usize) -> Recipe {
+        let mut recipe = self.root.clone();
+        recipe.effects = self.effects[index];
+        let mut node = index;
+        while node > 0 {
+            let (parent, i) = self.links[node - 1];
+            recipe.intermediates.push(i);
+            node = parent as usize;
+        }
+        recipe.intermediates.reverse();
+
+        recipe
+    }
+}
+
+/// Every effect set reachable from every fresh `Base` with up to a given number of ingredients, built once with any allowed ingredient and saved to disk.
+///
+/// Since the database stores the same nodes as the `StateSpace` of each base, its queries return the same recipes as the corresponding breadth-first searches, only without exploring anything.
+pub struct ReachableDatabase {
+    depth: i8,
+    graphs: Vec<ReachableGraph>,
+}
+
+impl std::fmt::Debug for ReachableDatabase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReachableDatabase")
+            .field("depth", &self.depth)
+            .field("states", &self.states())
+            .finish()
+    }
+}
+
+impl ReachableDatabase {
+    /// Explores every effect set reachable from every fresh `Base` with up to `depth` ingredients, or returns `None` if `cancel` is tripped first.
+    ///
+    /// Every expansion of every base is reported to `progress` as one stage.
+    pub fn build(
+        depth: i8,
+        cancel: &CancellationToken,
+        progress: &ProgressReporter,
+    ) -> Option<Self> {
+        let depth = depth.clamp(0, MAX_INGREDIENTS as i8);
+        progress.begin_stages(Base::ALL.len() as u64 * depth as u64);
+
+        let mut graphs = Vec::new();
+        for base in Base::ALL {
+            let root = Recipe::with_base(*base);
+            let mut space = StateSpace::new(root.clone(), EnumSet::all());
+            while space.depth() < depth && !cancel.is_cancelled() {
+                space.expand(cancel, progress);
+                progress.complete_stage();
+            }
+            if cancel.is_cancelled() {
+                return None;
+            }
+
+            let links = space
+                .all()
+                .skip(1)
+                .map(|index| {
+                    let (parent, i) = space.link(index).expect("only the root has no parent");
+                    (parent as u32, i)
+                })
+                .collect();
+            let graph = ReachableGraph::from_links(root, links);
+            graphs.push(graph.expect("a `StateSpace` is always stored layer by layer"));
+        }
+
+        Some(Self { depth, graphs })
+    }
+
+    /// Returns the number of ingredients of the longest recipes in this database.
+    pub fn depth(&self) -> i8 {
+        self.depth
+    }
+
+    /// Returns the number of effect sets stored in this database, counting every base separately.
+    pub fn states(&self) -> usize {
+        self.graphs.iter().map(|g| g.effects.len()).sum()
+    }
+
+    /// Writes this database to `path` in a versioned binary format.
+    ///
+    /// Only the link to the parent of every node is written, the rest is recomputed when loading.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(32 + self.states() * 5);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&DATABASE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&rules_fingerprint().to_le_bytes());
+        bytes.push(self.depth as u8);
+        bytes.push(self.graphs.len() as u8);
+        for graph in &self.graphs {
+            let base = Base::ALL.iter().position(|b| *b == graph.root.base());
+            bytes.push(base.expect("every base is in `Base::ALL`") as u8);
+            bytes.extend_from_slice(&(graph.links.len() as u32).to_le_bytes());
+            for (parent, i) in &graph.links {
+                bytes.extend_from_slice(&parent.to_le_bytes());
+                bytes.push(*i as u8);
+            }
+        }
+
+        std::fs::write(path, bytes)
+    }
+
+    /// Reads a database written by `save`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DatabaseError> {
+        let bytes = std::fs::read(path)?;
+        let mut reader = Reader(&bytes);
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(DatabaseError::NotADatabase);
+        }
+        let version = reader.u32()?;
+        if version != DATABASE_VERSION {
+            return Err(DatabaseError::UnsupportedVersion(version));
+        }
+        if reader.u64()? != rules_fingerprint() {
+            return Err(DatabaseError::OutdatedRules);
+        }
+
+        let depth = reader.u8()?;
+        if depth as usize > MAX_INGREDIENTS || reader.u8()? as usize != Base::ALL.len() {
+            return Err(DatabaseError::Corrupt);
+        }
+        let depth = depth as i8;
+        let graphs = Base::ALL
+            .iter()
+            .map(|_| {
+                let base = *Base::ALL
+                    .get(reader.u8()? as usize)
+                    .ok_or(DatabaseError::Corrupt)?;
+                let links = (0..reader.u32()?)
+                    .map(|_| {
+                        let parent = reader.u32()?;
+                        let i = *Intermediate::ALL
+                            .get(reader.u8()? as usize)
+                            .ok_or(DatabaseError::Corrupt)?;
+                        Ok((parent, i))
+                    })
+                    .collect::<Result<_, DatabaseError>>()?;
+                ReachableGraph::from_links(Recipe::with_base(base), links)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Every query expects one graph per base, explored as deep as the database claims.
+        let every_base_once = Base::ALL
+            .iter()
+            .all(|b| graphs.iter().filter(|g| g.root.base() == *b).count() == 1);
+        if !reader.0.is_empty() || !every_base_once || graphs.iter().any(|g| g.depth() != depth) {
+            return Err(DatabaseError::Corrupt);
+        }
+
+        Ok(Self { depth, graphs })
+    }
+
+    /// Returns the same recipe as `search_for_recipe_find_bfs` starting from every fresh base with every ingredient allowed, for a predicate on the base and effects of a recipe.
+    ///
+    /// Only the recipes whose effects satisfy `f` are rebuilt, which keeps a lookup within milliseconds. `depth` is capped to the depth of this database.
+    pub fn find_shortest<F>(&self, f: F, depth: i8) -> Option<Recipe>
+    where
+        F: Fn(Base, EnumSet<Effect>) -> bool + Sync + std::marker::Send,
+    {
+        let f = &f;
+        (0..=depth.clamp(0, self.depth)).find_map(|d| {
+            self.graphs
+                .par_iter()
+                .flat_map(|graph| {
+                    let base = graph.root.base();
+                    graph
+                        .layer(d)
+                        .into_par_iter()
+                        .filter(move |i| f(base, graph.effects[*i]))
+                        .map(|i| graph.recipe(i))
+                })
+                .min_by(Recipe::cmp_tie_break)
+        })
+    }
+
+    /// Returns the cheapest recipe with at most `depth` ingredients starting from any fresh base that satisfies `f`, like `search_for_recipe_find_cheapest_ucs` with every ingredient allowed.
+    ///
+    /// `f` is given the base and effects of a recipe, like in `find_shortest`.
+    /// When several of the cheapest recipes satisfy `f`, the one preferred by `Recipe::cmp_tie_break` is returned. `depth` is capped to the depth of this database.
+    pub fn find_cheapest<F>(&self, f: F, expenses: Expenses, depth: i8) -> Option<Recipe>
+    where
+        F: Fn(Base, EnumSet<Effect>) -> bool + Sync + std::marker::Send,
+    {
+        self.graphs
+            .par_iter()
+            .flat_map(|graph| {
+                let base = graph.root.base();
+                let base_cost = base.production_cost(expenses);
+                let matching: Vec<usize> = graph
+                    .best(depth)
+                    .par_iter()
+                    .copied()
+                    .filter(|i| f(base, graph.effects[*i]))
+                    .collect();
+                matching
+                    .into_par_iter()
+                    .map(move |i| (base_cost + graph.costs[i], graph.recipe(i)))
+            })
+            // Production costs are negative, so the cheapest recipe has the highest cost.
+            .min_by(|(a_cost, a), (b_cost, b)| {
+                b_cost.total_cmp(a_cost).then_with(|| a.cmp_tie_break(b))
+            })
+            .map(|(_, r)| r)
+    }
+
+    /// Returns the same recipe as `search_for_recipe_max_bfs` starting from a fresh `base` with every ingredient allowed.
+    ///
+    /// `depth` is capped to the depth of this database.
+    pub fn max<K, F>(&self, base: Base, f: F, depth: i8) -> Recipe
+    where
+        K: Ord + Send,
+        F: Fn(&Recipe) -> K + Sync + std::marker::Send,
+    {
+        self.scored(base, f, depth)
+            .max_by(cmp_scored)
+            .expect("the root is always part of the database")
+            .0
+    }
+
+    /// Returns the same recipes as `search_for_recipes_top_k_bfs` starting from a fresh `base` with every ingredient allowed.
+    ///
+    /// `depth` is capped to the depth of this database.
+    pub fn top_k<K, F>(&self, base: Base, f: F, depth: i8, k: usize) -> Vec<(Recipe, K)>
+    where
+        K: Ord + Send,
+        F: Fn(&Recipe) -> K + Sync + std::marker::Send,
+    {
+        let mut scored_recipes: Vec<(Recipe, K)> = self.scored(base, f, depth).collect();
+        scored_recipes.par_sort_by(|a, b| cmp_scored(b, a));
+        scored_recipes.truncate(k);
+
+        scored_recipes
+    }
+
+    /// Returns the cheapest recipe for every effect set reachable from `base` with at most `depth` ingredients alongside its value.
+    fn scored<K, F>(&self, base: Base, f: F, depth: i8) -> impl ParallelIterator<Item = (Recipe, K)>
+    where
+        K: Ord + Send,
+        F: Fn(&Recipe) -> K + Sync + std::marker::Send,
+    {
+        let graph = self
+            .graphs
+            .iter()
+            .find(|g| g.root.base() == base)
+            .expect("the database contains every base");
+
+        graph
+            .best(depth)
+            .into_owned()
+            .into_par_iter()
+            .map(move |i| {
+                let r = graph.recipe(i);
+                let key = f(&r);
+                (r, key)
+            })
+    }
+}
+
+/// Reads little-endian values from the front of a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DatabaseError> {
+        if self.0.len() < len {
+            return Err(DatabaseError::Corrupt);
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, DatabaseError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, DatabaseError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, DatabaseError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    fmt::Debug,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// The minimum time between two calls to a `ProgressReporter`'s callback.
+const REPORT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A snapshot of how far along a search is.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchProgress {
+    /// The estimated fraction of the search that is done, between `0.0` and `1.0`.
+    pub fraction: f32,
+    /// The number of recipes the search has gone through so far.
+    pub nodes_visited: u64,
+    /// The time since the search started.
+    pub elapsed: Duration,
+}
+
+impl SearchProgress {
+    /// Estimates the time left until the search finishes, assuming the remaining work goes as fast as the work done so far.
+    pub fn eta(&self) -> Option<Duration> {
+        (self.fraction > 0.0).then(|| {
+            self.elapsed
+                .mul_f32((1.0 - self.fraction).max(0.0) / self.fraction)
+        })
+    }
+}
+
+/// Statistics about the work done by a search.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// The number of recipes whose continuations were generated.
+    pub nodes_expanded: u64,
+    /// The number of recipes the objective or predicate of the search was evaluated on.
+    pub leaves_evaluated: u64,
+    /// The number of distinct effect sets the search went through, for the searches that keep track of them.
+    pub unique_effect_sets: Option<u64>,
+    /// The number of recipes that were skipped along with their continuations, because another recipe was at least as good or they provably couldn't beat the best one found so far.
+    pub nodes_pruned: u64,
+    /// The time the search took.
+    pub elapsed: Duration,
+    /// The number of threads the search ran on.
+    pub threads: usize,
+}
+
+struct ProgressState {
+    start: Instant,
+    /// The time of the last call to the callback, in milliseconds since `start`.
+    last_report: AtomicU64,
+    nodes_visited: AtomicU64,
+    nodes_expanded: AtomicU64,
+    leaves_evaluated: AtomicU64,
+    nodes_pruned: AtomicU64,
+    stages_total: AtomicU64,
+    stages_done: AtomicU64,
+    work_total: AtomicU64,
+    work_done: AtomicU64,
+    /// The time the search is allowed to run for in milliseconds, or `0` if it isn't time-limited.
+    budget: AtomicU64,
+    callback: Option<Box<dyn Fn(SearchProgress) + Send + Sync>>,
+}
+
+/// Collects the progress of a search and periodically hands it to a callback.
+///
+/// A search is split into stages, such as top-level branches or depths, which can each be split further into units of work.
+#[derive(Clone)]
+pub struct ProgressReporter(Arc<ProgressState>);
+
+impl Default for ProgressReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for ProgressReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ProgressReporter")
+            .field(&self.snapshot())
+            .finish()
+    }
+}
+
+impl ProgressReporter {
+    /// Creates a `ProgressReporter` that only keeps track of the progress without reporting it.
+    pub fn new() -> Self {
+        Self::from_callback(None)
+    }
+
+    /// Creates a `ProgressReporter` that calls `callback` at most every 50ms while the search makes progress.
+    pub fn with_callback<C>(callback: C) -> Self
+    where
+        C: Fn(SearchProgress) + Send + Sync + 'static,
+    {
+        Self::from_callback(Some(Box::new(callback)))
+    }
+
+    fn from_callback(callback: Option<Box<dyn Fn(SearchProgress) + Send + Sync>>) -> Self {
+        let now = Instant::now();
+        Self(Arc::new(ProgressState {
+            start: now,
+            last_report: AtomicU64::new(0),
+            nodes_visited: AtomicU64::new(0),
+            nodes_expanded: AtomicU64::new(0),
+            leaves_evaluated: AtomicU64::new(0),
+            nodes_pruned: AtomicU64::new(0),
+            stages_total: AtomicU64::new(0),
+            stages_done: AtomicU64::new(0),
+            work_total: AtomicU64::new(0),
+            work_done: AtomicU64::new(0),
+            budget: AtomicU64::new(0),
+            callback,
+        }))
+    }
+
+    /// Returns the current progress.
+    pub fn snapshot(&self) -> SearchProgress {
+        let state = &self.0;
+        let stages_total = state.stages_total.load(Ordering::Relaxed);
+        let stages_done = state.stages_done.load(Ordering::Relaxed);
+        let work_total = state.work_total.load(Ordering::Relaxed);
+        let work_done = state.work_done.load(Ordering::Relaxed);
+
+        let stage_fraction = match work_total {
+            0 => 0.0,
+            _ => work_done as f32 / work_total as f32,
+        };
+        let elapsed = state.start.elapsed();
+        let fraction = match (state.budget.load(Ordering::Relaxed), stages_total) {
+            (0, 0) => 0.0,
+            (0, _) => ((stages_done as f32 + stage_fraction) / stages_total as f32).min(1.0),
+            (budget, _) => (elapsed.as_millis() as f32 / budget as f32).min(1.0),
+        };
+
+        SearchProgress {
+            fraction,
+            nodes_visited: state.nodes_visited.load(Ordering::Relaxed),
+            elapsed,
+        }
+    }
+
+    /// Declares that the search is made of `stages` stages.
+    pub fn begin_stages(&self, stages: u64) {
+        self.0.stages_total.store(stages, Ordering::Relaxed);
+        self.0.stages_done.store(0, Ordering::Relaxed);
+        self.report();
+    }
+
+    /// Declares that the search runs for `budget` after this `ProgressReporter` was created, so that its progress is the fraction of `budget` that has elapsed rather than its completed stages.
+    pub fn begin_budget(&self, budget: Duration) {
+        self.0
+            .budget
+            .store((budget.as_millis() as u64).max(1), Ordering::Relaxed);
+        self.report();
+    }
+
+    /// Marks the current stage as done and discards its units of work.
+    pub fn complete_stage(&self) {
+        self.0.stages_done.fetch_add(1, Ordering::Relaxed);
+        self.0.work_total.store(0, Ordering::Relaxed);
+        self.0.work_done.store(0, Ordering::Relaxed);
+        self.report();
+    }
+
+    /// Adds `units` units of work to the current stage.
+    pub fn add_work(&self, units: u64) {
+        self.0.work_total.fetch_add(units, Ordering::Relaxed);
+    }
+
+    /// Marks `units` units of work of the current stage as done.
+    pub fn complete_work(&self, units: u64) {
+        self.0.work_done.fetch_add(units, Ordering::Relaxed);
+        self.report();
+    }
+
+    /// Records that `nodes` more recipes have been gone through.
+    pub fn visit(&self, nodes: u64) {
+        self.0.nodes_visited.fetch_add(nodes, Ordering::Relaxed);
+    }
+
+    /// Records that the continuations of `nodes` more recipes have been generated.
+    pub fn expand(&self, nodes: u64) {
+        self.0.nodes_expanded.fetch_add(nodes, Ordering::Relaxed);
+    }
+
+    /// Records that the objective or predicate of the search has been evaluated on `nodes` more recipes.
+    pub fn evaluate(&self, nodes: u64) {
+        self.0.leaves_evaluated.fetch_add(nodes, Ordering::Relaxed);
+    }
+
+    /// Records that `nodes` more recipes have been skipped along with their continuations.
+    pub fn prune(&self, nodes: u64) {
+        self.0.nodes_pruned.fetch_add(nodes, Ordering::Relaxed);
+    }
+
+    /// Adds the recipes gone through, expanded, evaluated and pruned by the search `other` reported to.
+    pub fn absorb(&self, other: &ProgressReporter) {
+        self.visit(other.0.nodes_visited.load(Ordering::Relaxed));
+        self.expand(other.0.nodes_expanded.load(Ordering::Relaxed));
+        self.evaluate(other.0.leaves_evaluated.load(Ordering::Relaxed));
+        self.prune(other.0.nodes_pruned.load(Ordering::Relaxed));
+    }
+
+    /// Starts measuring the work reported from now on, to be turned into the `SearchStats` of a search once it finishes.
+    pub(super) fn record_stats(&self) -> StatsRecorder {
+        StatsRecorder {
+            progress: self.clone(),
+            start: Instant::now(),
+            baseline: self.counters(),
+        }
+    }
+
+    /// Returns the number of recipes expanded, evaluated and pruned so far.
+    fn counters(&self) -> [u64; 3] {
+        [
+            self.0.nodes_expanded.load(Ordering::Relaxed),
+            self.0.leaves_evaluated.load(Ordering::Relaxed),
+            self.0.nodes_pruned.load(Ordering::Relaxed),
+        ]
+    }
+
+    /// Calls the callback if enough time has passed since the last call.
+    fn report(&self) {
+        let Some(callback) = &self.0.callback else {
+            return;
+        };
+
+        let now = self.0.start.elapsed().as_millis() as u64;
+        let last_report = self.0.last_report.load(Ordering::Relaxed);
+        if now < last_report + REPORT_INTERVAL.as_millis() as u64 {
+            return;
+        }
+
+        // Only the thread that manages to update the timestamp reports, the others would be redundant.
+        if self
+            .0
+            .last_report
+            .compare_exchange(last_report, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            callback(self.snapshot());
+        }
+    }
+}
+
+/// Measures the work reported to a `ProgressReporter` since `ProgressReporter::record_stats` was called.
+pub(super) struct StatsRecorder {
+    progress: ProgressReporter,
+    start: Instant,
+    baseline: [u64; 3],
+}
+
+impl StatsRecorder {
+    /// Returns the statistics of the search, with the number of distinct effect sets it went through if it keeps track of them.
+    pub fn finish(&self, unique_effect_sets: Option<u64>) -> SearchStats {
+        let [nodes_expanded, leaves_evaluated, nodes_pruned] = self.progress.counters();
+        let [expanded_before, evaluated_before, pruned_before] = self.baseline;
+
+        SearchStats {
+            nodes_expanded: nodes_expanded - expanded_before,
+            leaves_evaluated: leaves_evaluated - evaluated_before,
+            unique_effect_sets,
+            nodes_pruned: nodes_pruned - pruned_before,
+            elapsed: self.start.elapsed(),
+            threads: rayon::current_num_threads(),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use super::{CancellationToken, ProgressReporter};
+use crate::{effect::Effect, ingredients::Intermediate, recipe::Recipe};
+use ahash::AHashMap;
+use enumset::EnumSet;
+use rayon::prelude::*;
+
+/// A mixture reached while exploring the state space.
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    effects: EnumSet<Effect>,
+    /// The summed purchase price of the ingredients added since the root, as a negative `f32`.
+    cost: f32,
+    parent: Option<usize>,
+    intermediate: Option<Intermediate>,
+}
+
+/// The space of mixtures reachable from a root `Recipe`, deduplicated by effect set.
+///
+/// Since the future of a mix depends only on its base and its current effects, every recipe reaching the same effect set is interchangeable apart from its cost and length.
+/// Only the cheapest recipe reaching each effect set is kept, with ties going to the shortest one and then to the first one in lexicographic order of ingredients.
+pub(super) struct StateSpace {
+    root: Recipe,
+    nodes: Vec<Node>,
+    best: AHashMap<EnumSet<Effect>, usize>,
+    frontier: Vec<usize>,
+    depth: i8,
+    allowed: EnumSet<Intermediate>,
+}
+
+impl StateSpace {
+    /// Creates a `StateSpace` containing only `root`, which can only be expanded with `allowed` ingredients.
+    pub fn new(root: Recipe, allowed: EnumSet<Intermediate>) -> Self {
+        let root_node = Node {
+            effects: root.calculate_effects(),
+            cost: 0.0,
+            parent: None,
+            intermediate: None,
+        };
+
+        Self {
+            root,
+            nodes: vec![root_node],
+            best: AHashMap::from([(root_node.effects, 0)]),
+            frontier: vec![0],
+            depth: 0,
+            allowed,
+        }
+    }
+
+    /// Creates a `StateSpace` from `root` and expands it `depth` times, stopping early if `cancel` is tripped.
+    ///
+    /// Every expansion is reported to `progress` as one stage.
+    pub fn explore(
+        root: Recipe,
+        depth: i8,
+        allowed: EnumSet<Intermediate>,
+        cancel: &CancellationToken,
+        progress: &ProgressReporter,
+    ) -> Self {
+        let mut space = Self::new(root, allowed);
+        progress.begin_stages(depth.max(0) as u64);
+        while space.depth < depth && !cancel.is_cancelled() {
+            space.expand(cancel, progress);
+            progress.complete_stage();
+        }
+
+        space
+    }
+
+    /// Returns how many ingredients have been added to the root so far.
+    pub fn depth(&self) -> i8 {
+        self.depth
+    }
+
+    /// Returns the nodes that were admitted by the last call to `expand`.
+    pub fn frontier(&self) -> &[usize] {
+        &self.frontier
+    }
+
+    /// Returns the number of distinct effect sets found so far.
+    pub fn unique_effect_sets(&self) -> u64 {
+        self.best.len() as u64
+    }
+
+    /// Returns the cheapest node found for every distinct effect set.
+    pub fn best(&self) -> impl Iterator<Item = usize> + '_ {
+        self.best.values().copied()
+    }
+
+    /// Returns every node admitted so far.
+    ///
+    /// For every reachable effect set this contains all recipes that are not both longer and at least as expensive as another recipe reaching it.
+    pub fn all(&self) -> std::ops::Range<usize> {
+        0..self.nodes.len()
+    }
+
+    /// Adds one more allowed ingredient to every node of the frontier.
+    ///
+    /// A child is only admitted if it is strictly cheaper than every other recipe found for its effect set, as those are at most as long.
+    /// If `cancel` is tripped midway, only part of the frontier gets expanded.
+    ///
+    /// The frontier is kept in lexicographic order of ingredients, so that the children are generated in that order too and the first of several equally cheap ones can be kept.
+    ///
+    /// Every node of the frontier is reported to `progress` as one unit of work, and every child that isn't admitted as pruned.
+    pub fn expand(&mut self, cancel: &CancellationToken, progress: &ProgressReporter) {
+        progress.add_work(self.frontier.len() as u64);
+        let allowed = self.allowed;
+        let children: Vec<Node> = self
+            .frontier
+            .par_iter()
+            .filter(|_| !cancel.is_cancelled())
+            .flat_map_iter(|&parent| {
+                progress.visit(allowed.len() as u64);
+                progress.expand(1);
+                progress.complete_work(1);
+                let node = self.nodes[parent];
+                allowed.iter().map(move |i| {
+                    let mut effects = node.effects;
+                    i.apply_to_effect_set(&mut effects);
+                    Node {
+                        effects,
+                        cost: node.cost + i.purchase_price(),
+                        parent: Some(parent),
+                        intermediate: Some(i),
+                    }
+                })
+            })
+            .collect();
+        let child_count = children.len();
+
+        // Every child is stored alongside its position in `children`, which is its lexicographic rank.
+        let mut layer: AHashMap<EnumSet<Effect>, (usize, Node)> = AHashMap::new();
+        for (rank, child) in children.into_iter().enumerate() {
+            let dominated = self
+                .best
+                .get(&child.effects)
+                .is_some_and(|&i| self.nodes[i].cost >= child.cost)
+                || layer
+                    .get(&child.effects)
+                    .is_some_and(|(_, n)| n.cost >= child.cost);
+            if !dominated {
+                layer.insert(child.effects, (rank, child));
+            }
+        }
+        let mut layer: Vec<(usize, Node)> = layer.into_values().collect();
+        layer.sort_unstable_by_key(|(rank, _)| *rank);
+        progress.prune((child_count - layer.len()) as u64);
+
+        self.frontier.clear();
+        for (_, node) in layer {
+            let index = self.nodes.len();
+            self.nodes.push(node);
+            self.best.insert(node.effects, index);
+            self.frontier.push(index);
+        }
+        self.depth += 1;
+    }
+
+    /// Returns the effect set of the node at `index`.
+    pub fn effects(&self, index: usize) -> EnumSet<Effect> {
+        self.nodes[index].effects
+    }
+
+    /// Returns the summed purchase price of the ingredients added to the root by the node at `index`, as a negative `f32`.
+    pub fn cost(&self, index: usize) -> f32 {
+        self.nodes[index].cost
+    }
+
+    /// Returns the node that the node at `index` was reached from and the ingredient added to it, or `None` for the root.
+    pub fn link(&self, index: usize) -> Option<(usize, Intermediate)> {
+        let node = &self.nodes[index];
+        node.parent.zip(node.intermediate)
+    }
+
+    /// Rebuilds the `Recipe` leading to the node at `index`.
+    pub fn recipe(&self, index: usize) -> Recipe {
+        let mut intermediates = Vec::new();
+        let mut node = &self.nodes[index];
+        while let (Some(parent), Some(i)) = (node.parent, node.intermediate) {
+            intermediates.push(i);
+            node = &self.nodes[parent];
+        }
+
+        intermediates
+            .into_iter()
+            .rev()
+            .fold(self.root.clone(), Recipe::add_intermediate)
+    }
+}
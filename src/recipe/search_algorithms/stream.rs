@@ -0,0 +1,124 @@
+use super::{CancellationToken, ProgressReporter, StateSpace};
+use crate::{effect::Effect, ingredients::Intermediate, recipe::Recipe};
+use ahash::AHashSet;
+use enumset::EnumSet;
+
+/// The recipes yielded by `search_for_recipes_all_iddfs`.
+pub struct AllRecipes<P> {
+    root: Recipe,
+    f: P,
+    depth: i8,
+    allowed: Vec<Intermediate>,
+    /// The number of ingredients added to `root` by the recipes currently being yielded.
+    length: i8,
+    /// The recipes leading to the next one to check, each with the position in `allowed` of the next ingredient to add to it.
+    stack: Vec<(Recipe, usize)>,
+}
+
+impl<P> AllRecipes<P> {
+    pub(super) fn new(root: Recipe, f: P, depth: i8, allowed: EnumSet<Intermediate>) -> Self {
+        Self {
+            stack: vec![(root.clone(), 0)],
+            root,
+            f,
+            depth: depth.max(0),
+            allowed: Intermediate::ALL
+                .iter()
+                .copied()
+                .filter(|i| allowed.contains(*i))
+                .collect(),
+            length: 0,
+        }
+    }
+}
+
+impl<P> Iterator for AllRecipes<P>
+where
+    P: Fn(&Recipe) -> bool,
+{
+    type Item = Recipe;
+
+    fn next(&mut self) -> Option<Recipe> {
+        loop {
+            let complete = self.stack.len() == self.length as usize + 1;
+            let Some((recipe, next)) = self.stack.last_mut() else {
+                if self.length >= self.depth {
+                    return None;
+                }
+                self.length += 1;
+                self.stack.push((self.root.clone(), 0));
+                continue;
+            };
+
+            if complete {
+                let (recipe, _) = self.stack.pop().expect("the stack isn't empty");
+                if (self.f)(&recipe) {
+                    return Some(recipe);
+                }
+            } else if let Some(&i) = self.allowed.get(*next) {
+                *next += 1;
+                let child = recipe.clone().add_intermediate(i);
+                self.stack.push((child, 0));
+            } else {
+                self.stack.pop();
+            }
+        }
+    }
+}
+
+/// The recipes yielded by `search_for_recipes_all_bfs`.
+pub struct AllEffectSets<P> {
+    space: StateSpace,
+    f: P,
+    depth: i8,
+    /// Every effect set reached so far, whether it was yielded or not.
+    seen: AHashSet<EnumSet<Effect>>,
+    /// The position in the frontier of the next node to check.
+    next: usize,
+    cancel: CancellationToken,
+    progress: ProgressReporter,
+}
+
+impl<P> AllEffectSets<P> {
+    pub(super) fn new(root: Recipe, f: P, depth: i8, allowed: EnumSet<Intermediate>) -> Self {
+        Self {
+            space: StateSpace::new(root, allowed),
+            f,
+            depth,
+            seen: AHashSet::new(),
+            next: 0,
+            cancel: CancellationToken::new(),
+            progress: ProgressReporter::new(),
+        }
+    }
+}
+
+impl<P> Iterator for AllEffectSets<P>
+where
+    P: Fn(&Recipe) -> bool,
+{
+    type Item = Recipe;
+
+    fn next(&mut self) -> Option<Recipe> {
+        loop {
+            let Some(&index) = self.space.frontier().get(self.next) else {
+                if self.space.depth() >= self.depth {
+                    return None;
+                }
+                self.space.expand(&self.cancel, &self.progress);
+                self.next = 0;
+                continue;
+            };
+            self.next += 1;
+
+            // Effect sets reached again by a cheaper but longer recipe were already checked.
+            if !self.seen.insert(self.space.effects(index)) {
+                continue;
+            }
+            let recipe = self.space.recipe(index);
+            if (self.f)(&recipe) {
+                return Some(recipe);
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+/// The threads searches run on, for `ThreadSettings::build_pool`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ThreadSettings {
+    /// The number of worker threads, or `0` for one per logical core.
+    pub threads: usize,
+    /// Whether the worker threads ask the operating system to only run them when nothing else needs the CPU.
+    pub low_priority: bool,
+}
+
+impl ThreadSettings {
+    /// Builds a dedicated `ThreadPool` with these settings.
+    ///
+    /// Every search parallelizes on the pool it is called from, so running it with `ThreadPool::install` keeps it off rayon's global pool.
+    pub fn build_pool(&self) -> Result<ThreadPool, ThreadPoolBuildError> {
+        let low_priority = self.low_priority;
+        ThreadPoolBuilder::new()
+            .num_threads(self.threads)
+            .thread_name(|i| format!("search-{i}"))
+            .start_handler(move |_| {
+                if low_priority {
+                    lower_thread_priority();
+                }
+            })
+            .build()
+    }
+}
+
+/// Asks the operating system to only run the calling thread when nothing else needs the CPU.
+#[cfg(target_os = "linux")]
+fn lower_thread_priority() {
+    // Linux gives every thread its own nice value, so this leaves the rest of the process alone.
+    // SAFETY: `setpriority` only reads its arguments, and failing leaves the priority unchanged.
+    unsafe {
+        libc::setpriority(libc::PRIO_PROCESS, 0, 19);
+    }
+}
+
+/// Asks the operating system to only run the calling thread when nothing else needs the CPU.
+#[cfg(target_vendor = "apple")]
+fn lower_thread_priority() {
+    // SAFETY: This only changes the quality of service of the calling thread, and failing leaves it unchanged.
+    unsafe {
+        libc::pthread_set_qos_class_self_np(libc::qos_class_t::QOS_CLASS_UTILITY, 0);
+    }
+}
+
+/// Asks the operating system to only run the calling thread when nothing else needs the CPU.
+#[cfg(windows)]
+fn lower_thread_priority() {
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_LOWEST,
+    };
+
+    // SAFETY: The pseudo handle of the current thread is always valid, and failing leaves the priority unchanged.
+    unsafe {
+        SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_LOWEST);
+    }
+}
+
+/// Thread priorities aren't supported on this platform, so low priority threads run like any other.
+#[cfg(not(any(target_os = "linux", target_vendor = "apple", windows)))]
+fn lower_thread_priority() {}
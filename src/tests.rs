@@ -1,9 +1,12 @@
-use crate::effect::Effect;
+use crate::effect::{Effect, EffectConstraint, EffectConstraints};
 use crate::expenses::Expenses;
 use crate::ingredients::{Base, Intermediate};
-use crate::recipe::Recipe;
+use crate::recipe::expression::Expression;
+use crate::recipe::metric::{Metric, Scoring};
+use crate::recipe::search_algorithms::*;
+use crate::recipe::{MAX_EFFECTS, MAX_INGREDIENTS, Recipe, RecipeEdit, StartingState};
+use enumset::EnumSet;
 
-#[expect(unused_macros)]
 macro_rules! time {
     ($($stmt:stmt)*) => {
         let then = std::time::Instant::now();
@@ -39,6 +42,87 @@ fn calculate_interactions_test() {
     }
 }
 
+#[test]
+fn apply_to_effect_set_test() {
+    let mix = |effects: EnumSet<Effect>, ingredient: Intermediate| {
+        let mut effects = effects;
+        ingredient.apply_to_effect_set(&mut effects);
+        effects
+    };
+
+    // These cases pin down the rules the calculator mixes by, they aren't results observed in the game.
+    // Effects created by an interaction never trigger another one, Smelly would turn into Anti-Gravity otherwise.
+    assert_eq!(
+        mix(Effect::Toxic.into(), Intermediate::Banana),
+        Effect::Smelly | Effect::Gingeritis
+    );
+    // An effect is kept if what it turns into is already there.
+    assert_eq!(
+        mix(Effect::Calming | Effect::Sneaky, Intermediate::Banana),
+        Effect::Calming | Effect::Sneaky | Effect::Gingeritis
+    );
+    // Chains are applied in alphabetical order, so Calming turns into Slippery before Foggy turns into Calming.
+    assert_eq!(
+        mix(Effect::Calming | Effect::Foggy, Intermediate::Paracetamol),
+        Effect::Slippery | Effect::Calming | Effect::Sneaky
+    );
+    // The ingredient's own effect isn't added to a full product, but its interactions still apply.
+    let full = Effect::Calming
+        | Effect::Toxic
+        | Effect::Athletic
+        | Effect::Balding
+        | Effect::Electrifying
+        | Effect::Glowing
+        | Effect::Munchies
+        | Effect::Zombifying;
+    assert_eq!(
+        mix(full, Intermediate::Banana),
+        (full - (Effect::Calming | Effect::Toxic)) | Effect::Sneaky | Effect::Smelly
+    );
+}
+
+#[test]
+fn compact_recipe_test() {
+    for ingredient in Intermediate::ALL {
+        for effect in Effect::ALL {
+            assert_eq!(
+                ingredient.interaction(effect),
+                ingredient.interactions().get(effect).copied()
+            );
+        }
+    }
+
+    // The effects kept up to date by every kind of change match the ones of the same recipe built from scratch.
+    let r = Recipe::with_base(Base::GreenCrack)
+        .add_intermediate(Intermediate::Paracetamol)
+        .add_intermediate(Intermediate::Donut)
+        .add_intermediate(Intermediate::Cuke);
+    for edit in RecipeEdit::all(&r, EnumSet::all()) {
+        let edited = edit.apply(&r);
+        let rebuilt = edited
+            .intermediates()
+            .iter()
+            .fold(Recipe::with_start(edited.start()), |r, i| {
+                r.add_intermediate(*i)
+            });
+        assert_eq!(edited.calculate_effects(), rebuilt.calculate_effects());
+    }
+
+    let full = (0..MAX_INGREDIENTS).fold(Recipe::with_base(Base::Meth), |r, _| {
+        r.add_intermediate(Intermediate::Cuke)
+    });
+    assert_eq!(full.intermediates().len(), MAX_INGREDIENTS);
+    assert!(
+        RecipeEdit::all(&full, EnumSet::all())
+            .iter()
+            .all(|e| matches!(e, RecipeEdit::Replace { .. }))
+    );
+    let too_long = std::iter::repeat_n("Cuke", MAX_INGREDIENTS + 1)
+        .collect::<Vec<_>>()
+        .join(" + ");
+    assert!(format!("Meth + {too_long}").parse::<Recipe>().is_err());
+}
+
 #[test]
 fn test() {
     let mut e = Expenses::default();
@@ -46,3 +130,1503 @@ fn test() {
     let recipe = Recipe::with_base(Base::Meth).add_intermediate(Intermediate::Addy);
     println!("{}", recipe.production_cost(e));
 }
+
+#[test]
+fn search_for_recipe_max_bfs_matches_dfs_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    for base in Base::ALL {
+        let f = |r: &Recipe| (100.0 * r.profit(Expenses::default())) as i64;
+        let bfs = search_for_recipe_max_bfs(
+            Recipe::with_base(*base),
+            f,
+            3,
+            EnumSet::all(),
+            &cancel,
+            &progress,
+        )
+        .0;
+        let dfs = search_for_recipe_max_dfs(
+            Recipe::with_base(*base),
+            f,
+            3,
+            EnumSet::all(),
+            &cancel,
+            &progress,
+        )
+        .0;
+        assert_eq!(f(&bfs), f(&dfs));
+    }
+}
+
+#[test]
+fn search_for_recipe_find_bfs_test() {
+    let target = Effect::CalorieDense
+        | Effect::Gingeritis
+        | Effect::Jennerising
+        | Effect::Sneaky
+        | Effect::ThoughtProvoking;
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let recipe = search_for_recipe_find_bfs(
+        &StartingState::every_base(),
+        |r| target.is_subset(r.calculate_effects()),
+        8,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0
+    .expect("the default target is reachable");
+    assert!(target.is_subset(recipe.calculate_effects()));
+    assert!(recipe.intermediates().len() <= 4);
+}
+
+#[test]
+fn search_for_recipes_top_k_bfs_test() {
+    let f = |r: &Recipe| (100.0 * r.profit(Expenses::default())) as i64;
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let best = search_for_recipe_max_bfs(
+        Recipe::with_base(Base::OGKush),
+        f,
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0;
+    let top = search_for_recipes_top_k_bfs(
+        Recipe::with_base(Base::OGKush),
+        f,
+        3,
+        EnumSet::all(),
+        10,
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert_eq!(top.len(), 10);
+    assert_eq!(top[0].1, f(&best));
+    assert!(top.windows(2).all(|w| w[0].1 >= w[1].1));
+    for (i, (a, _)) in top.iter().enumerate() {
+        for (b, _) in &top[i + 1..] {
+            assert_ne!(a.calculate_effects(), b.calculate_effects());
+        }
+    }
+}
+
+#[test]
+fn search_for_recipes_pareto_bfs_test() {
+    let f = |r: &Recipe| {
+        vec![
+            r.profit(Expenses::default()),
+            -(r.intermediates().len() as f32),
+        ]
+    };
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let frontier = search_for_recipes_pareto_bfs(
+        Recipe::with_base(Base::Meth),
+        f,
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0;
+
+    // The empty recipe is the only one with no steps, so it is always on the frontier.
+    assert!(frontier.iter().any(|(r, _)| r.intermediates().is_empty()));
+    for (a, values_a) in &frontier {
+        assert_eq!(&f(a), values_a);
+        for (_, values_b) in &frontier {
+            assert!(values_a == values_b || values_a.iter().zip(values_b).any(|(a, b)| a > b));
+        }
+    }
+
+    let best = search_for_recipe_max_bfs(
+        Recipe::with_base(Base::Meth),
+        |r| (100.0 * r.profit(Expenses::default())) as i64,
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert_eq!(frontier[0].1[0], best.profit(Expenses::default()));
+}
+
+#[test]
+fn cancelled_search_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    cancel.cancel();
+    let f = |r: &Recipe| (100.0 * r.profit(Expenses::default())) as i64;
+
+    let recipe = search_for_recipe_max_bfs(
+        Recipe::with_base(Base::Meth),
+        f,
+        8,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert!(recipe.intermediates().is_empty());
+    let recipe = search_for_recipe_max_dfs(
+        Recipe::with_base(Base::Meth),
+        f,
+        8,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert!(recipe.intermediates().is_empty());
+    assert!(
+        search_for_recipe_find_bfs(
+            &StartingState::every_base(),
+            |r| r.intermediates().len() == 8,
+            8,
+            EnumSet::all(),
+            &cancel,
+            &progress
+        )
+        .0
+        .is_none()
+    );
+    assert!(
+        search_for_recipe_find_iddfs(
+            &StartingState::every_base(),
+            |r| r.intermediates().len() == 8,
+            8,
+            EnumSet::all(),
+            TieBreak::Any,
+            &cancel,
+            &progress
+        )
+        .0
+        .is_none()
+    );
+}
+
+#[test]
+fn search_progress_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let f = |r: &Recipe| (100.0 * r.profit(Expenses::default())) as i64;
+
+    search_for_recipe_max_bfs(
+        Recipe::with_base(Base::Meth),
+        f,
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    );
+    let snapshot = progress.snapshot();
+    assert_eq!(snapshot.fraction, 1.0);
+    assert!(snapshot.nodes_visited >= 16);
+    assert_eq!(snapshot.eta(), Some(std::time::Duration::ZERO));
+
+    let progress = ProgressReporter::new();
+    search_for_recipe_max_dfs(
+        Recipe::with_base(Base::Meth),
+        f,
+        2,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    );
+    let snapshot = progress.snapshot();
+    assert_eq!(snapshot.fraction, 1.0);
+    assert_eq!(snapshot.nodes_visited, 1 + 16 + 16 * 16);
+}
+
+#[test]
+fn search_for_recipe_max_branch_and_bound_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let expenses = Expenses::default();
+    for base in [Base::OGKush, Base::Meth] {
+        for metric in Metric::ALL {
+            let f = |r: &Recipe| (1000.0 * metric.value(r, expenses)) as i64;
+            let brute_force = search_for_recipe_max_dfs(
+                Recipe::with_base(base),
+                f,
+                3,
+                EnumSet::all(),
+                &cancel,
+                &progress,
+            )
+            .0;
+            let (recipe, stats) = search_for_recipe_max_branch_and_bound(
+                Recipe::with_base(base),
+                Scoring::from(*metric),
+                expenses,
+                3,
+                EnumSet::all(),
+                &cancel,
+                &progress,
+            );
+            assert_eq!(f(&recipe), f(&brute_force));
+            assert!(stats.leaves_evaluated < 1 + 16 + 16 * 16 + 16 * 16 * 16);
+            assert!(stats.nodes_pruned > 0);
+        }
+    }
+}
+
+#[test]
+fn search_for_recipe_find_cheapest_ucs_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let expenses = Expenses {
+        grow_tent: true,
+        ..Default::default()
+    };
+    let target = Effect::Sneaky | Effect::Slippery;
+    let f = |r: &Recipe| target.is_subset(r.calculate_effects());
+
+    let cheapest = search_for_recipe_find_cheapest_ucs(
+        &StartingState::every_base(),
+        f,
+        expenses,
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0
+    .expect("the target is reachable");
+    assert!(f(&cheapest));
+
+    let key = |r: &Recipe| (f(r), (100.0 * r.production_cost(expenses)).round() as i64);
+    let brute_force = Base::ALL
+        .iter()
+        .map(|b| {
+            search_for_recipe_max_dfs(
+                Recipe::with_base(*b),
+                key,
+                3,
+                EnumSet::all(),
+                &cancel,
+                &progress,
+            )
+            .0
+        })
+        .max_by_key(key)
+        .expect("there is at least one base");
+    assert_eq!(key(&cheapest), key(&brute_force));
+
+    let shortest = search_for_recipe_find_bfs(
+        &StartingState::every_base(),
+        f,
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0
+    .expect("the target is reachable");
+    assert!(cheapest.production_cost(expenses) >= shortest.production_cost(expenses));
+}
+
+#[test]
+fn effect_constraints_test() {
+    let mut constraints = EffectConstraints::requiring(Effect::Sneaky | Effect::Slippery);
+    constraints.set(Effect::Toxic, EffectConstraint::Forbidden);
+    assert_eq!(constraints.get(Effect::Sneaky), EffectConstraint::Required);
+    assert_eq!(constraints.get(Effect::Toxic), EffectConstraint::Forbidden);
+    assert_eq!(constraints.get(Effect::Calming), EffectConstraint::DontCare);
+
+    assert!(constraints.is_satisfied_by(Effect::Sneaky | Effect::Slippery | Effect::Calming));
+    assert!(!constraints.is_satisfied_by(Effect::Sneaky | Effect::Slippery | Effect::Toxic));
+    assert!(!constraints.is_satisfied_by(Effect::Sneaky.into()));
+
+    constraints.exact = true;
+    assert_eq!(
+        constraints.get(Effect::Calming),
+        EffectConstraint::Forbidden
+    );
+    assert!(constraints.is_satisfied_by(Effect::Sneaky | Effect::Slippery));
+    assert!(!constraints.is_satisfied_by(Effect::Sneaky | Effect::Slippery | Effect::Calming));
+
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let recipe = search_for_recipe_find_bfs(
+        &StartingState::every_base(),
+        |r| constraints.is_satisfied_by(r.calculate_effects()),
+        8,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0
+    .expect("Sneaky and Slippery alone are reachable");
+    assert_eq!(
+        recipe.calculate_effects(),
+        Effect::Sneaky | Effect::Slippery
+    );
+}
+
+#[test]
+fn allowed_ingredients_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let allowed = EnumSet::all() - (Intermediate::Cuke | Intermediate::Banana);
+    let f = |r: &Recipe| (100.0 * r.profit(Expenses::default())) as i64;
+
+    let bfs = search_for_recipe_max_bfs(
+        Recipe::with_base(Base::Meth),
+        f,
+        3,
+        allowed,
+        &cancel,
+        &progress,
+    )
+    .0;
+    let dfs = search_for_recipe_max_dfs(
+        Recipe::with_base(Base::Meth),
+        f,
+        3,
+        allowed,
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert_eq!(f(&bfs), f(&dfs));
+    assert!(bfs.intermediates().iter().all(|i| allowed.contains(*i)));
+    assert!(dfs.intermediates().iter().all(|i| allowed.contains(*i)));
+
+    let unrestricted = search_for_recipe_max_bfs(
+        Recipe::with_base(Base::Meth),
+        f,
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert!(f(&unrestricted) >= f(&bfs));
+
+    let only_cuke = search_for_recipe_find_bfs(
+        &StartingState::every_base(),
+        |r| !r.intermediates().is_empty(),
+        3,
+        Intermediate::Cuke.into(),
+        &cancel,
+        &progress,
+    )
+    .0
+    .expect("a single Cuke is a valid recipe");
+    assert_eq!(only_cuke.intermediates(), &[Intermediate::Cuke]);
+}
+
+#[test]
+fn search_for_recipes_every_base_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let f = |r: &Recipe| (100.0 * r.profit(Expenses::default())) as i64;
+    let results = search_for_recipes_every_base(
+        |root, cancel, progress| {
+            search_for_recipe_max_bfs(root, f, 2, EnumSet::all(), cancel, progress).0
+        },
+        &cancel,
+        &progress,
+    )
+    .0;
+
+    assert_eq!(results.len(), Base::ALL.len());
+    for ((base, recipe), expected_base) in results.iter().zip(Base::ALL) {
+        assert_eq!(base, expected_base);
+        assert_eq!(recipe.base(), *base);
+        let single = search_for_recipe_max_bfs(
+            Recipe::with_base(*base),
+            f,
+            2,
+            EnumSet::all(),
+            &cancel,
+            &ProgressReporter::new(),
+        )
+        .0;
+        assert_eq!(f(recipe), f(&single));
+    }
+    assert_eq!(progress.snapshot().fraction, 1.0);
+    assert!(progress.snapshot().nodes_visited > 0);
+}
+
+#[test]
+fn weighted_scoring_branch_and_bound_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let expenses = Expenses {
+        grow_tent: true,
+        ..Default::default()
+    };
+    for scoring in [
+        Scoring {
+            metric: Metric::ProfitMargin,
+            metric_weight: 1.0,
+            addictiveness_weight: 1.0,
+        },
+        Scoring {
+            metric: Metric::Profit,
+            metric_weight: 0.0,
+            addictiveness_weight: 1.0,
+        },
+    ] {
+        let f = |r: &Recipe| (1000.0 * scoring.value(r, expenses)) as i64;
+        let brute_force = search_for_recipe_max_dfs(
+            Recipe::with_base(Base::SourDiesel),
+            f,
+            3,
+            EnumSet::all(),
+            &cancel,
+            &progress,
+        )
+        .0;
+        let (recipe, _) = search_for_recipe_max_branch_and_bound(
+            Recipe::with_base(Base::SourDiesel),
+            scoring,
+            expenses,
+            3,
+            EnumSet::all(),
+            &cancel,
+            &progress,
+        );
+        assert_eq!(f(&recipe), f(&brute_force));
+    }
+
+    let r = Recipe::with_base(Base::OGKush).add_intermediate(Intermediate::Cuke);
+    let scoring = Scoring::from(Metric::Profit);
+    assert_eq!(scoring.value(&r, expenses), r.profit(expenses));
+    assert_ne!(scoring.value(&r, expenses), r.profit(Expenses::default()));
+}
+
+#[test]
+fn expression_test() {
+    let expenses = Expenses::default();
+    let r = Recipe::with_base(Base::OGKush)
+        .add_intermediate(Intermediate::Cuke)
+        .add_intermediate(Intermediate::Banana);
+
+    let expression = Expression::parse("profit * 0.7 + addictiveness * 20 - steps * 2")
+        .expect("the expression is valid");
+    let expected = r.profit(expenses) * 0.7 + r.addictiveness() * 20.0 - 2.0 * 2.0;
+    assert!((expression.value(&r, expenses) - expected).abs() < 1e-4);
+
+    let has_toxic = r.calculate_effects().contains(Effect::Toxic);
+    let expression = Expression::parse("sell_price where !Toxic").expect("the expression is valid");
+    match has_toxic {
+        true => assert_eq!(expression.value(&r, expenses), f32::NEG_INFINITY),
+        false => assert_eq!(expression.value(&r, expenses), r.sell_price()),
+    }
+    assert_eq!(expression.accepts(&r, expenses), !has_toxic);
+    let impossible =
+        Expression::parse("profit where steps > 100").expect("the expression is valid");
+    assert!(!impossible.accepts(&r, expenses));
+    assert!(Expression::parse("profit").unwrap().accepts(&r, expenses));
+
+    let expression = Expression::parse("-(1 + 2) * 3 < -8 && (Sneaky || !sneaky)")
+        .expect("the expression is valid");
+    assert_eq!(expression.value(&r, expenses), 1.0);
+
+    let f = |r: &Recipe| (100.0 * r.profit(expenses)).round() as i64;
+    let expression = Expression::parse("profit").expect("the expression is valid");
+    let g = |r: &Recipe| (100.0 * expression.value(r, expenses)).round() as i64;
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let by_metric = search_for_recipe_max_dfs(
+        Recipe::with_base(Base::Meth),
+        f,
+        2,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0;
+    let by_expression = search_for_recipe_max_dfs(
+        Recipe::with_base(Base::Meth),
+        g,
+        2,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert_eq!(f(&by_metric), g(&by_expression));
+}
+
+#[test]
+fn expression_error_test() {
+    let error = Expression::parse("proft * 2").expect_err("`proft` is misspelled");
+    assert_eq!(error.span, 0..5);
+    assert!(error.message.contains("did you mean `profit`"));
+
+    let error = Expression::parse("profit * (steps + 1").expect_err("the parenthesis is unclosed");
+    assert_eq!(error.span, 9..10);
+
+    let error = Expression::parse("profit = 2").expect_err("`=` is not an operator");
+    assert_eq!(error.span, 7..8);
+
+    let error = Expression::parse("profit +").expect_err("the sum is incomplete");
+    assert!(error.message.contains("end of the objective"));
+
+    let error = Expression::parse("profit steps").expect_err("an operator is missing");
+    assert_eq!(error.span, 7..12);
+
+    assert!(Expression::parse("").is_err());
+    assert!(Expression::parse("profit where Toxic where Sneaky").is_err());
+
+    // Deep nesting is an error rather than a stack overflow.
+    let nested = |depth: usize| format!("{}profit{}", "(".repeat(depth), ")".repeat(depth));
+    assert!(Expression::parse(&nested(200)).is_ok());
+    let error =
+        Expression::parse(&nested(100_000)).expect_err("the objective is nested too deeply");
+    assert!(error.message.contains("nested"));
+    assert!(Expression::parse(&"-".repeat(100_000)).is_err());
+    assert!(Expression::parse(&"!".repeat(100_000)).is_err());
+    assert!(Expression::parse(&vec!["1"; 100_000].join(" + ")).is_err());
+}
+
+#[test]
+fn heuristic_searches_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let expenses = Expenses::default();
+    let f = |r: &Recipe| (100.0 * r.profit(expenses)) as i64;
+    let root = Recipe::with_base(Base::OGKush);
+
+    let exact = search_for_recipe_max_bfs(root.clone(), f, 3, EnumSet::all(), &cancel, &progress).0;
+    let beam = search_for_recipe_max_beam(
+        root.clone(),
+        f,
+        3,
+        EnumSet::all(),
+        usize::MAX,
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert_eq!(f(&beam), f(&exact));
+
+    let long_beam =
+        search_for_recipe_max_beam(root.clone(), f, 12, EnumSet::all(), 50, &cancel, &progress).0;
+    assert!(long_beam.intermediates().len() <= 12);
+    assert!(f(&long_beam) >= f(&beam));
+
+    let g = |r: &Recipe| r.profit(expenses);
+    let annealed = search_for_recipe_max_annealing(
+        root.clone(),
+        g,
+        12,
+        EnumSet::all(),
+        20_000,
+        42,
+        TieBreak::Deterministic,
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert!(annealed.intermediates().len() <= 12);
+    assert!(g(&annealed) > g(&root));
+    let again = search_for_recipe_max_annealing(
+        root.clone(),
+        g,
+        12,
+        EnumSet::all(),
+        20_000,
+        42,
+        TieBreak::Deterministic,
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert_eq!(annealed.intermediates(), again.intermediates());
+}
+
+#[test]
+fn long_root_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let expenses = Expenses::default();
+    let scoring = Scoring::from(Metric::Profit);
+    let f = |r: &Recipe| (100.0 * r.profit(expenses)) as i64;
+    let long = |n| {
+        (0..n).fold(Recipe::with_base(Base::Meth), |r, _| {
+            r.add_intermediate(Intermediate::Cuke)
+        })
+    };
+    let fits = |r: &Recipe| r.intermediates().len() <= MAX_INGREDIENTS;
+
+    // Searches never go past `MAX_INGREDIENTS`, however deep they are asked to go.
+    let beam =
+        search_for_recipe_max_beam(long(20), f, 20, EnumSet::all(), 50, &cancel, &progress).0;
+    assert!(fits(&beam));
+
+    let root = long(MAX_INGREDIENTS - 2);
+    let bfs = search_for_recipe_max_bfs(root.clone(), f, 8, EnumSet::all(), &cancel, &progress).0;
+    let dfs = search_for_recipe_max_dfs(root.clone(), f, 8, EnumSet::all(), &cancel, &progress).0;
+    assert_eq!(f(&bfs), f(&dfs));
+    let branch_and_bound = search_for_recipe_max_branch_and_bound(
+        root.clone(),
+        scoring,
+        expenses,
+        8,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0;
+    let anytime = search_for_recipe_max_anytime(
+        root.clone(),
+        scoring,
+        expenses,
+        std::time::Duration::from_millis(100),
+        EnumSet::all(),
+        |_| {},
+        &cancel,
+        &progress,
+    )
+    .0;
+    let annealing = search_for_recipe_max_annealing(
+        root.clone(),
+        |r| r.profit(expenses),
+        8,
+        EnumSet::all(),
+        1000,
+        0,
+        TieBreak::Deterministic,
+        &cancel,
+        &progress,
+    )
+    .0;
+    for r in [&bfs, &dfs, &branch_and_bound, &anytime, &annealing] {
+        assert!(fits(r));
+    }
+
+    let never = |_: &Recipe| false;
+    let (found, _) = search_for_recipe_find_dfs(
+        root.clone(),
+        never,
+        8,
+        EnumSet::all(),
+        TieBreak::Any,
+        &cancel,
+        &progress,
+    );
+    assert!(found.is_none());
+    assert_eq!(
+        search_for_recipes_all_iddfs(root.clone(), |_| true, 8, EnumSet::all()).count(),
+        1 + 16 + 16 * 16
+    );
+    assert!(
+        search_for_recipes_all_bfs(root.clone(), |_| true, 8, EnumSet::all()).all(|r| fits(&r))
+    );
+
+    let full = long(MAX_INGREDIENTS);
+    let beam =
+        search_for_recipe_max_beam(full.clone(), f, 20, EnumSet::all(), 50, &cancel, &progress).0;
+    assert_eq!(beam.intermediates(), full.intermediates());
+}
+
+#[test]
+fn search_for_recipe_max_anytime_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let expenses = Expenses::default();
+    let scoring = Scoring::from(Metric::Profit);
+    let root = Recipe::with_base(Base::OGKush);
+
+    let improvements = std::sync::Mutex::new(Vec::new());
+    let budget = std::time::Duration::from_millis(500);
+    let then = std::time::Instant::now();
+    let recipe = search_for_recipe_max_anytime(
+        root.clone(),
+        scoring,
+        expenses,
+        budget,
+        EnumSet::all(),
+        |r| {
+            improvements
+                .lock()
+                .expect("the callback never panics")
+                .push(scoring.value(r, expenses))
+        },
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert!(then.elapsed() < budget + std::time::Duration::from_secs(1));
+    assert!(!cancel.is_cancelled());
+    assert_eq!(progress.snapshot().fraction, 1.0);
+
+    let improvements = improvements
+        .into_inner()
+        .expect("the callback never panics");
+    assert!(improvements.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(improvements.last(), Some(&scoring.value(&recipe, expenses)));
+
+    let (shallow, _) = search_for_recipe_max_branch_and_bound(
+        root,
+        scoring,
+        expenses,
+        2,
+        EnumSet::all(),
+        &cancel,
+        &ProgressReporter::new(),
+    );
+    assert!(scoring.value(&recipe, expenses) >= scoring.value(&shallow, expenses));
+}
+
+#[test]
+fn parse_recipe_test() {
+    let recipe = Recipe::with_base(Base::OGKush)
+        .add_intermediate(Intermediate::Paracetamol)
+        .add_intermediate(Intermediate::EnergyDrink)
+        .add_intermediate(Intermediate::MegaBean);
+
+    let parsed: Recipe = "og kush + Paracetamol -> energy drink, Mega Bean"
+        .parse()
+        .expect("the recipe is valid");
+    assert_eq!(parsed.base(), recipe.base());
+    assert_eq!(parsed.intermediates(), recipe.intermediates());
+
+    let parsed: Recipe = recipe.to_string().parse().expect("the recipe is valid");
+    assert_eq!(parsed.base(), recipe.base());
+    assert_eq!(parsed.intermediates(), recipe.intermediates());
+
+    assert!("Meth".parse::<Recipe>().is_ok());
+    assert!("".parse::<Recipe>().is_err());
+    assert!("Cuke + Meth".parse::<Recipe>().is_err());
+    assert!("Meth + Pickle".parse::<Recipe>().is_err());
+}
+
+#[test]
+fn improve_existing_recipe_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let f = |r: &Recipe| (100.0 * r.profit(Expenses::default())) as i64;
+    let root = Recipe::with_base(Base::Meth)
+        .add_intermediate(Intermediate::Cuke)
+        .add_intermediate(Intermediate::Banana);
+
+    let bfs = search_for_recipe_max_bfs(root.clone(), f, 2, EnumSet::all(), &cancel, &progress).0;
+    let dfs = search_for_recipe_max_dfs(root.clone(), f, 2, EnumSet::all(), &cancel, &progress).0;
+    assert_eq!(f(&bfs), f(&dfs));
+    assert_eq!(&bfs.intermediates()[..2], root.intermediates());
+
+    let edits = search_for_recipes_top_k_single_edit(
+        root.clone(),
+        f,
+        EnumSet::all(),
+        usize::MAX,
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert_eq!(edits.len(), 3 * 16 + 2 * 15);
+    assert!(edits.windows(2).all(|w| w[0].2 >= w[1].2));
+    for (edit, r, key) in &edits {
+        assert_eq!(edit.apply(&root).intermediates(), r.intermediates());
+        assert_eq!(f(r), *key);
+    }
+
+    let inserted = RecipeEdit::Insert {
+        index: 1,
+        intermediate: Intermediate::Donut,
+    }
+    .apply(&root);
+    assert_eq!(
+        inserted.intermediates(),
+        &[
+            Intermediate::Cuke,
+            Intermediate::Donut,
+            Intermediate::Banana
+        ]
+    );
+    let replaced = RecipeEdit::Replace {
+        index: 0,
+        intermediate: Intermediate::Donut,
+    }
+    .apply(&root);
+    assert_eq!(
+        replaced.intermediates(),
+        &[Intermediate::Donut, Intermediate::Banana]
+    );
+}
+
+#[test]
+fn starting_state_test() {
+    let start = StartingState {
+        base: Base::OGKush,
+        effects: Effect::Sneaky | Effect::Calming | Effect::Toxic,
+    };
+    assert!(!start.is_fresh());
+    assert!(StartingState::from(Base::Meth).is_fresh());
+
+    let r = Recipe::with_start(start).add_intermediate(Intermediate::Cuke);
+    let mut expected = start.effects;
+    Intermediate::Cuke.apply_to_effect_set(&mut expected);
+    assert_eq!(r.calculate_effects(), expected);
+    assert_eq!(
+        r.production_cost(Expenses::default()),
+        Recipe::with_base(Base::OGKush)
+            .add_intermediate(Intermediate::Cuke)
+            .production_cost(Expenses::default())
+    );
+
+    let parsed: Recipe = "og kush [toxic, Sneaky, calming] + Cuke"
+        .parse()
+        .expect("the recipe is valid");
+    assert_eq!(parsed.start(), start);
+    assert_eq!(
+        parsed.start().to_string(),
+        "OG Kush [Calming, Sneaky, Toxic]"
+    );
+    let reparsed: Recipe = parsed.to_string().parse().expect("to_string can be parsed");
+    assert_eq!(reparsed.start(), start);
+    assert_eq!(reparsed.intermediates(), &vec![Intermediate::Cuke]);
+    assert!("OG Kush [Sneaky, Wet] + Cuke".parse::<Recipe>().is_err());
+    assert!(
+        "OG Kush [Sneaky, Calming, Toxic, Foggy, Energizing, Gingeritis, Munchies, Slippery, Spicy]"
+            .parse::<Recipe>()
+            .is_err()
+    );
+
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let f = |r: &Recipe| (100.0 * r.profit(Expenses::default())) as i64;
+    let bfs = search_for_recipe_max_bfs(
+        Recipe::with_start(start),
+        f,
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0;
+    let dfs = search_for_recipe_max_dfs(
+        Recipe::with_start(start),
+        f,
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0;
+    assert_eq!(bfs.start(), start);
+    assert_eq!(f(&bfs), f(&dfs));
+
+    let already_sneaky = search_for_recipe_find_bfs(
+        &[start],
+        |r| r.calculate_effects().contains(Effect::Sneaky),
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0
+    .expect("the start is already Sneaky");
+    assert!(already_sneaky.intermediates().is_empty());
+}
+
+#[test]
+fn deterministic_tie_break_test() {
+    let cuke = Recipe::with_base(Base::OGKush).add_intermediate(Intermediate::Cuke);
+    let banana = Recipe::with_base(Base::OGKush).add_intermediate(Intermediate::Banana);
+    let addy = Recipe::with_base(Base::OGKush).add_intermediate(Intermediate::Addy);
+    assert!(Recipe::with_base(Base::Meth).cmp_tie_break(&cuke).is_lt());
+    assert!(banana.cmp_tie_break(&cuke).is_lt());
+    assert!(cuke.cmp_tie_break(&addy).is_lt());
+    assert!(cuke.cmp_tie_break(&cuke).is_eq());
+
+    // Counting effects ties almost every recipe, so any scheduling-dependent choice would show up.
+    let f = |r: &Recipe| r.calculate_effects().len();
+    let scoring = Scoring::from(Metric::SellPrice);
+    let run = |threads: usize| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("the thread pool can be built");
+        pool.install(|| {
+            let cancel = CancellationToken::new();
+            let progress = ProgressReporter::new();
+            let root = Recipe::with_base(Base::OGKush);
+            let target = Effect::Sneaky | Effect::Slippery;
+            [
+                search_for_recipe_max_bfs(root.clone(), f, 4, EnumSet::all(), &cancel, &progress).0,
+                search_for_recipe_max_dfs(root.clone(), f, 3, EnumSet::all(), &cancel, &progress).0,
+                search_for_recipe_max_beam(
+                    root.clone(),
+                    f,
+                    5,
+                    EnumSet::all(),
+                    20,
+                    &cancel,
+                    &progress,
+                )
+                .0,
+                search_for_recipe_max_branch_and_bound(
+                    root.clone(),
+                    scoring,
+                    Expenses::default(),
+                    3,
+                    EnumSet::all(),
+                    &cancel,
+                    &progress,
+                )
+                .0,
+                search_for_recipe_max_annealing(
+                    root.clone(),
+                    |r| scoring.value(r, Expenses::default()),
+                    6,
+                    EnumSet::all(),
+                    2_000,
+                    7,
+                    TieBreak::Deterministic,
+                    &cancel,
+                    &progress,
+                )
+                .0,
+                search_for_recipe_find_dfs(
+                    root.clone(),
+                    |r| target.is_subset(r.calculate_effects()),
+                    3,
+                    EnumSet::all(),
+                    TieBreak::Deterministic,
+                    &cancel,
+                    &progress,
+                )
+                .0
+                .expect("the target is reachable"),
+                search_for_recipe_find_bfs(
+                    &StartingState::every_base(),
+                    |r| target.is_subset(r.calculate_effects()),
+                    3,
+                    EnumSet::all(),
+                    &cancel,
+                    &progress,
+                )
+                .0
+                .expect("the target is reachable"),
+            ]
+            .map(|r| r.to_string())
+        })
+    };
+
+    assert_eq!(run(1), run(4));
+}
+
+#[test]
+#[ignore = "benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+fn depth_6_search_benchmark() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let root = Recipe::with_base(Base::OGKush);
+    let f = |r: &Recipe| (100.0 * r.profit(Expenses::default())) as i64;
+
+    println!("max DFS:");
+    time! {
+        let _ = search_for_recipe_max_dfs(root.clone(), f, 6, EnumSet::all(), &cancel, &progress)
+    }
+    println!("max BFS:");
+    time! {
+        let _ = search_for_recipe_max_bfs(root.clone(), f, 6, EnumSet::all(), &cancel, &progress)
+    }
+    println!("branch and bound:");
+    time! {
+        let _ = search_for_recipe_max_branch_and_bound(
+            root.clone(),
+            Scoring::from(Metric::Profit),
+            Expenses::default(),
+            6,
+            EnumSet::all(),
+            &cancel,
+            &progress,
+        )
+    }
+    println!("find DFS:");
+    time! {
+        let _ = search_for_recipe_find_dfs(
+            root.clone(),
+            |r| r.intermediates().len() == 7,
+            6,
+            EnumSet::all(),
+            TieBreak::Any,
+            &cancel,
+            &progress,
+        )
+    }
+
+    let starts: Vec<StartingState> = Base::ALL.iter().map(|b| StartingState::from(*b)).collect();
+    let target = Recipe::with_base(Base::Meth)
+        .add_intermediate(Intermediate::Battery)
+        .add_intermediate(Intermediate::Chilli)
+        .add_intermediate(Intermediate::Iodine)
+        .add_intermediate(Intermediate::MotorOil)
+        .add_intermediate(Intermediate::Addy)
+        .add_intermediate(Intermediate::Viagra)
+        .add_intermediate(Intermediate::Gasoline)
+        .calculate_effects();
+    let constraints = EffectConstraints::requiring(target);
+    println!("find BFS, {} effects:", target.len());
+    time! {
+        let _ = search_for_recipe_find_bfs(
+            &starts,
+            |r| constraints.is_satisfied_by(r.calculate_effects()),
+            8,
+            EnumSet::all(),
+            &cancel,
+            &progress,
+        )
+    }
+    println!("find bidirectional, {} effects:", target.len());
+    time! {
+        let _ = search_for_recipe_find_bidirectional(
+            &starts,
+            constraints,
+            8,
+            EnumSet::all(),
+            &cancel,
+            &progress,
+        )
+    }
+}
+
+/// Mixes `ingredient` into `effects` the way recipes did before the transition table, looking every effect up in `Intermediate::interactions`.
+fn mix_with_hash_map(ingredient: Intermediate, effects: &mut EnumSet<Effect>) {
+    let frozen = *effects;
+    for from in frozen {
+        if let Some(to) = ingredient.interactions().get(&from).copied()
+            && !effects.contains(to)
+        {
+            effects.remove(from);
+            effects.insert(to);
+        }
+    }
+    if effects.len() < MAX_EFFECTS {
+        effects.insert(ingredient.effect());
+    }
+}
+
+#[test]
+#[ignore = "benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+fn recipe_representation_benchmark() {
+    // Goes through every recipe of 6 ingredients on one thread, once with a `Vec` of ingredients whose effects are mixed from the base with hash map lookups as before, and once with `Recipe`.
+    fn with_vec(base: Base, ingredients: Vec<Intermediate>, depth: usize) -> f32 {
+        if depth == 0 {
+            let effects =
+                ingredients
+                    .iter()
+                    .fold(StartingState::from(base).effects, |mut effects, i| {
+                        mix_with_hash_map(*i, &mut effects);
+                        effects
+                    });
+            return effects.iter().map(|e| e.price_modifier()).sum();
+        }
+        Intermediate::ALL
+            .iter()
+            .map(|i| {
+                let mut child = ingredients.clone();
+                child.push(*i);
+                with_vec(base, child, depth - 1)
+            })
+            .sum()
+    }
+    fn with_recipe(recipe: &Recipe, depth: usize) -> f32 {
+        if depth == 0 {
+            return recipe
+                .calculate_effects()
+                .iter()
+                .map(|e| e.price_modifier())
+                .sum();
+        }
+        Intermediate::ALL
+            .iter()
+            .map(|i| with_recipe(&recipe.clone().add_intermediate(*i), depth - 1))
+            .sum()
+    }
+
+    let then = std::time::Instant::now();
+    let before = with_vec(Base::OGKush, Vec::new(), 6);
+    let vec_time = then.elapsed();
+    let then = std::time::Instant::now();
+    let after = with_recipe(&Recipe::with_base(Base::OGKush), 6);
+    let recipe_time = then.elapsed();
+
+    assert_eq!(before, after);
+    println!(
+        "Vec and hash maps: {:.2}ms\nRecipe: {:.2}ms\nspeedup: {:.1}x",
+        vec_time.as_secs_f64() * 1000.0,
+        recipe_time.as_secs_f64() * 1000.0,
+        vec_time.as_secs_f64() / recipe_time.as_secs_f64()
+    );
+}
+
+#[test]
+fn reachable_database_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let expenses = Expenses::default();
+    let database =
+        ReachableDatabase::build(3, &cancel, &progress).expect("the build isn't cancelled");
+
+    let path = std::env::temp_dir().join(format!(
+        "reachable_database_test_{}.bin",
+        std::process::id()
+    ));
+    database.save(&path).expect("the file can be written");
+    let loaded = ReachableDatabase::load(&path).expect("the file was just written");
+
+    // The header is followed by every graph, each made of its base, its number of links and 5 bytes per link.
+    let bytes = std::fs::read(&path).expect("the file was just written");
+    let header = 22;
+    let mut graphs = Vec::new();
+    let mut start = header;
+    while start < bytes.len() {
+        let links = u32::from_le_bytes(bytes[start + 1..start + 5].try_into().unwrap()) as usize;
+        graphs.push(&bytes[start..start + 5 + 5 * links]);
+        start += 5 + 5 * links;
+    }
+    assert_eq!(graphs.len(), Base::ALL.len());
+    let corrupt = |header_bytes: &[u8], graphs: &[&[u8]]| {
+        let mut file = header_bytes.to_vec();
+        file[header - 1] = graphs.len() as u8;
+        file.extend(graphs.concat());
+        std::fs::write(&path, file).expect("the file can be written");
+        matches!(ReachableDatabase::load(&path), Err(DatabaseError::Corrupt))
+    };
+    assert!(corrupt(&bytes[..header], &graphs[..1]));
+    assert!(corrupt(
+        &bytes[..header],
+        &[graphs[0], graphs[0], graphs[2], graphs[3], graphs[4]]
+    ));
+    let mut deeper = bytes[..header].to_vec();
+    deeper[header - 2] += 1;
+    assert!(corrupt(&deeper, &graphs));
+    std::fs::write(&path, &bytes[..bytes.len() - 1]).expect("the file can be written");
+    assert!(matches!(
+        ReachableDatabase::load(&path),
+        Err(DatabaseError::Corrupt)
+    ));
+
+    std::fs::write(&path, b"not a database").expect("the file can be written");
+    assert!(matches!(
+        ReachableDatabase::load(&path),
+        Err(DatabaseError::NotADatabase)
+    ));
+    std::fs::remove_file(&path).expect("the file exists");
+    assert_eq!(loaded.depth(), 3);
+    assert_eq!(loaded.states(), database.states());
+
+    let same = |a: &Recipe, b: &Recipe| {
+        a.base() == b.base()
+            && a.intermediates() == b.intermediates()
+            && a.calculate_effects() == b.calculate_effects()
+    };
+    let target = Effect::Sneaky | Effect::Slippery;
+    let f = |r: &Recipe| target.is_subset(r.calculate_effects());
+    let g = |_, effects: EnumSet<Effect>| target.is_subset(effects);
+    let shortest = search_for_recipe_find_bfs(
+        &StartingState::every_base(),
+        f,
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0
+    .expect("the target is reachable");
+    assert!(same(&loaded.find_shortest(g, 3).unwrap(), &shortest));
+
+    let cheapest = search_for_recipe_find_cheapest_ucs(
+        &StartingState::every_base(),
+        f,
+        expenses,
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    )
+    .0
+    .expect("the target is reachable");
+    let found = loaded.find_cheapest(g, expenses, 3).unwrap();
+    assert!(f(&found));
+    assert_eq!(
+        found.production_cost(expenses),
+        cheapest.production_cost(expenses)
+    );
+    assert_eq!(found.intermediates().len(), cheapest.intermediates().len());
+
+    let profit = |r: &Recipe| (100.0 * r.profit(expenses)) as i64;
+    for depth in [2, 3] {
+        let root = Recipe::with_base(Base::Meth);
+        let max = search_for_recipe_max_bfs(
+            root.clone(),
+            profit,
+            depth,
+            EnumSet::all(),
+            &cancel,
+            &progress,
+        )
+        .0;
+        assert!(same(&loaded.max(Base::Meth, profit, depth), &max));
+
+        let top = search_for_recipes_top_k_bfs(
+            root,
+            profit,
+            depth,
+            EnumSet::all(),
+            5,
+            &cancel,
+            &progress,
+        )
+        .0;
+        let loaded_top = loaded.top_k(Base::Meth, profit, depth, 5);
+        assert_eq!(top.len(), loaded_top.len());
+        for ((a, a_key), (b, b_key)) in top.iter().zip(&loaded_top) {
+            assert!(same(a, b));
+            assert_eq!(a_key, b_key);
+        }
+    }
+}
+
+#[test]
+fn search_stats_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let f = |r: &Recipe| (100.0 * r.profit(Expenses::default())) as i64;
+    let root = Recipe::with_base(Base::Meth);
+
+    let (_, dfs) =
+        search_for_recipe_max_dfs(root.clone(), f, 2, EnumSet::all(), &cancel, &progress);
+    assert_eq!(dfs.nodes_expanded, 1 + 16);
+    assert_eq!(dfs.leaves_evaluated, 1 + 16 + 16 * 16);
+    assert_eq!(dfs.nodes_pruned, 0);
+    assert_eq!(dfs.unique_effect_sets, None);
+    assert_eq!(dfs.threads, rayon::current_num_threads());
+
+    // The same reporter keeps counting, but the stats only cover the search they are returned by.
+    let (_, bfs) =
+        search_for_recipe_max_bfs(root.clone(), f, 2, EnumSet::all(), &cancel, &progress);
+    let unique_effect_sets = bfs.unique_effect_sets.expect("BFS tracks effect sets");
+    assert_eq!(bfs.leaves_evaluated, unique_effect_sets);
+    assert!(bfs.nodes_expanded <= dfs.nodes_expanded);
+    assert!(bfs.nodes_pruned > 0);
+
+    let (_, every_base) = search_for_recipes_every_base(
+        |root, cancel, progress| {
+            search_for_recipe_max_dfs(root, f, 1, EnumSet::all(), cancel, progress)
+        },
+        &cancel,
+        &progress,
+    );
+    assert_eq!(every_base.nodes_expanded, Base::ALL.len() as u64);
+    assert_eq!(
+        every_base.leaves_evaluated,
+        Base::ALL.len() as u64 * (1 + 16)
+    );
+}
+
+#[test]
+fn thread_settings_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let f = |r: &Recipe| (100.0 * r.profit(Expenses::default())) as i64;
+    let root = Recipe::with_base(Base::Meth);
+    let (expected, _) =
+        search_for_recipe_max_bfs(root.clone(), f, 2, EnumSet::all(), &cancel, &progress);
+
+    let settings = ThreadSettings {
+        threads: 2,
+        low_priority: true,
+    };
+    let pool = settings.build_pool().unwrap();
+    let (recipe, stats) = pool.install(|| {
+        search_for_recipe_max_bfs(root.clone(), f, 2, EnumSet::all(), &cancel, &progress)
+    });
+    assert_eq!(recipe.intermediates(), expected.intermediates());
+    assert_eq!(stats.threads, 2);
+}
+
+#[test]
+fn all_recipes_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let gingeritis = |r: &Recipe| r.calculate_effects().contains(Effect::Gingeritis);
+    let root = Recipe::with_base(Base::Meth);
+
+    let recipes: Vec<Recipe> =
+        search_for_recipes_all_iddfs(root.clone(), gingeritis, 3, EnumSet::all()).collect();
+    let mut layer = vec![root.clone()];
+    let mut expected = 0;
+    for _ in 0..3 {
+        layer = layer
+            .iter()
+            .flat_map(|r| {
+                Intermediate::ALL
+                    .iter()
+                    .map(|i| r.clone().add_intermediate(*i))
+            })
+            .collect();
+        expected += layer.iter().filter(|r| gingeritis(r)).count();
+    }
+    assert!(expected > 0);
+    assert_eq!(recipes.len(), expected);
+    assert!(recipes.is_sorted_by_key(|r| r.intermediates().len()));
+    let (first, _) = search_for_recipe_find_iddfs(
+        &[root.start()],
+        gingeritis,
+        3,
+        EnumSet::all(),
+        TieBreak::Deterministic,
+        &cancel,
+        &progress,
+    );
+    assert_eq!(
+        recipes[0].intermediates(),
+        first.expect("Banana adds Gingeritis").intermediates()
+    );
+
+    // Taking only the first few recipes doesn't go through the deeper layers.
+    let first_few: Vec<Recipe> =
+        search_for_recipes_all_iddfs(root.clone(), |_| true, 20, EnumSet::all())
+            .take(3)
+            .collect();
+    assert_eq!(first_few.len(), 3);
+    assert!(first_few[0].intermediates().is_empty());
+
+    let effect_sets: Vec<Recipe> =
+        search_for_recipes_all_bfs(root.clone(), |_| true, 3, EnumSet::all()).collect();
+    let (_, stats) = search_for_recipe_find_bfs(
+        &[root.start()],
+        |_| false,
+        3,
+        EnumSet::all(),
+        &cancel,
+        &progress,
+    );
+    assert_eq!(Some(effect_sets.len() as u64), stats.unique_effect_sets);
+    assert!(effect_sets.is_sorted_by_key(|r| r.intermediates().len()));
+    let distinct: ahash::AHashSet<_> = effect_sets.iter().map(|r| r.calculate_effects()).collect();
+    assert_eq!(distinct.len(), effect_sets.len());
+}
+
+#[test]
+fn batch_search_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let starts: Vec<StartingState> = Base::ALL.iter().map(|b| StartingState::from(*b)).collect();
+    let targets = [
+        EffectConstraints::requiring(Effect::Gingeritis | Effect::Sneaky),
+        EffectConstraints::requiring(Effect::Energizing | Effect::Calming | Effect::Toxic),
+        EffectConstraints::requiring(EnumSet::all()),
+    ];
+
+    let (results, stats) =
+        search_for_recipes_batch_bfs(&starts, &targets, 3, EnumSet::all(), &cancel, &progress);
+    assert_eq!(results.len(), targets.len());
+    assert!(stats.unique_effect_sets.is_some());
+    assert!(
+        results[2]
+            .iter()
+            .all(|r| r.shortest.is_none() && r.cheapest.is_none())
+    );
+
+    for (target, found) in targets.iter().zip(&results) {
+        let f = |r: &Recipe| target.is_satisfied_by(r.calculate_effects());
+        for (start, found) in starts.iter().zip(found) {
+            let (shortest, _) =
+                search_for_recipe_find_bfs(&[*start], f, 3, EnumSet::all(), &cancel, &progress);
+            assert_eq!(
+                found.shortest.as_ref().map(|r| r.intermediates().to_vec()),
+                shortest.map(|r| r.intermediates().to_vec())
+            );
+
+            let expenses = Expenses::default();
+            let (cheapest, _) = search_for_recipe_find_cheapest_ucs(
+                &[*start],
+                f,
+                expenses,
+                3,
+                EnumSet::all(),
+                &cancel,
+                &progress,
+            );
+            let cost =
+                |r: Option<&Recipe>| r.map(|r| (100.0 * r.production_cost(expenses)).round());
+            assert_eq!(cost(found.cheapest.as_ref()), cost(cheapest.as_ref()));
+        }
+    }
+}
+
+#[test]
+fn unapply_to_effect_set_test() {
+    let recipes =
+        search_for_recipes_all_bfs(Recipe::with_base(Base::OGKush), |_| true, 3, EnumSet::all());
+    for recipe in recipes.take(500) {
+        let effects = recipe.calculate_effects();
+        for i in Intermediate::ALL {
+            let mut mixed = effects;
+            i.apply_to_effect_set(&mut mixed);
+            let predecessors = i.unapply_to_effect_set(mixed);
+            assert!(predecessors.contains(&effects));
+            for predecessor in predecessors {
+                let mut remixed = predecessor;
+                i.apply_to_effect_set(&mut remixed);
+                assert_eq!(remixed, mixed);
+            }
+        }
+    }
+}
+
+#[test]
+fn bidirectional_search_test() {
+    let cancel = CancellationToken::new();
+    let progress = ProgressReporter::new();
+    let starts: Vec<StartingState> = Base::ALL.iter().map(|b| StartingState::from(*b)).collect();
+    let mut exact = EffectConstraints::requiring(
+        Effect::Calming | Effect::Gingeritis | Effect::Sneaky | Effect::ThoughtProvoking,
+    );
+    exact.exact = true;
+    let mut forbidding = EffectConstraints::requiring(Effect::Gingeritis | Effect::Sneaky);
+    forbidding.forbidden = Effect::Calming | Effect::Energizing;
+
+    for constraints in [
+        EffectConstraints::requiring(
+            Effect::Jennerising
+                | Effect::Sneaky
+                | Effect::Gingeritis
+                | Effect::ThoughtProvoking
+                | Effect::CalorieDense,
+        ),
+        EffectConstraints::requiring(Effect::Calming.into()),
+        // The shortest recipe with the cheapest ingredients doesn't start from the cheapest base.
+        EffectConstraints::requiring(Effect::AntiGravity | Effect::Athletic),
+        exact,
+        forbidding,
+    ] {
+        // Both searches pick the same recipe among the shortest ones, as does the database.
+        let f = |r: &Recipe| constraints.is_satisfied_by(r.calculate_effects());
+        let (expected, _) =
+            search_for_recipe_find_bfs(&starts, f, 5, EnumSet::all(), &cancel, &progress);
+        let (found, stats) = search_for_recipe_find_bidirectional(
+            &starts,
+            constraints,
+            5,
+            EnumSet::all(),
+            &cancel,
+            &progress,
+        );
+        assert!(stats.unique_effect_sets.is_some());
+        let summary = |r: Option<Recipe>| {
+            r.map(|r| {
+                assert!(f(&r));
+                (r.base(), r.intermediates().to_vec())
+            })
+        };
+        assert_eq!(summary(found), summary(expected));
+    }
+}